@@ -0,0 +1,444 @@
+use anyhow::Result;
+use axum::extract::{FromRef, Path, Query, State};
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::NaiveDate;
+use log::info;
+use serde::Deserialize;
+use serde_json::json;
+use std::convert::Infallible;
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::BroadcastStream;
+use tokio_stream::{Stream, StreamExt};
+
+use crate::cli_commands::{build_schedule, check_dependencies, ScheduleOptions};
+use crate::daemon::{self, DaemonState, RunTrigger};
+use crate::database::{Database, HistoryFilter, OutputFilter};
+use crate::live_output::{LiveOutputs, OutputEvent, OutputStream, RunningExecution};
+use crate::models::{ExecutionHistory, ExecutionStatus, ExecutionType, Schedule, ScheduleStatus};
+
+/// Bind the API listener on localhost
+pub async fn bind(port: u16) -> Result<TcpListener> {
+    Ok(TcpListener::bind(("127.0.0.1", port)).await?)
+}
+
+/// Serve the REST API until the listener fails
+pub async fn serve(listener: TcpListener, state: DaemonState) -> Result<()> {
+    axum::serve(listener, router(state)).await?;
+    Ok(())
+}
+
+pub fn router(state: DaemonState) -> Router {
+    Router::new()
+        .route("/api/health", get(health))
+        .route("/api/schedules", get(list_schedules).post(create_schedule))
+        .route(
+            "/api/schedules/{id}",
+            get(get_schedule).delete(delete_schedule),
+        )
+        .route("/api/schedules/{id}/run", post(run_schedule))
+        .route("/api/schedules/{id}/cancel", post(cancel_schedule))
+        .route("/api/history", get(list_history))
+        .route("/api/executions/running", get(list_running))
+        .route("/api/executions/{id}/stream", get(stream_execution))
+        .route("/api/executions/{id}/cancel", post(cancel_execution))
+        .with_state(state)
+}
+
+impl FromRef<DaemonState> for Database {
+    fn from_ref(state: &DaemonState) -> Self {
+        state.db.clone()
+    }
+}
+
+impl FromRef<DaemonState> for LiveOutputs {
+    fn from_ref(state: &DaemonState) -> Self {
+        state.outputs.clone()
+    }
+}
+
+/// Error response rendered as `{"error": "..."}`
+pub struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl ApiError {
+    fn bad_request(error: anyhow::Error) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: error.to_string(),
+        }
+    }
+
+    fn not_found(message: String) -> Self {
+        Self {
+            status: StatusCode::NOT_FOUND,
+            message,
+        }
+    }
+
+    fn conflict(message: String) -> Self {
+        Self {
+            status: StatusCode::CONFLICT,
+            message,
+        }
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(error: anyhow::Error) -> Self {
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: error.to_string(),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(json!({ "error": self.message }))).into_response()
+    }
+}
+
+type ApiResult<T> = std::result::Result<T, ApiError>;
+
+async fn health() -> Json<serde_json::Value> {
+    Json(json!({
+        "status": "ok",
+        "version": env!("CARGO_PKG_VERSION"),
+    }))
+}
+
+#[derive(Deserialize)]
+struct ScheduleQuery {
+    status: Option<String>,
+    limit: Option<usize>,
+}
+
+async fn list_schedules(
+    State(db): State<Database>,
+    Query(query): Query<ScheduleQuery>,
+) -> ApiResult<Json<Vec<Schedule>>> {
+    let status = query.status.as_deref().map(ScheduleStatus::from_string);
+    Ok(Json(db.get_schedules(status, query.limit).await?))
+}
+
+async fn get_schedule(
+    State(db): State<Database>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<Schedule>> {
+    let schedule = db
+        .get_schedule(&id)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("Schedule '{id}' not found")))?;
+    Ok(Json(schedule))
+}
+
+/// Request body for `POST /api/schedules`, mirroring the `schedule` command options
+#[derive(Deserialize)]
+struct CreateScheduleRequest {
+    command: String,
+    time: Option<String>,
+    date: Option<String>,
+    cron: Option<String>,
+    every: Option<String>,
+    until: Option<String>,
+    count: Option<u32>,
+    mode: Option<String>,
+    branch: Option<String>,
+    #[serde(default)]
+    worktree: bool,
+    memo: Option<String>,
+    execution_path: Option<String>,
+    #[serde(default)]
+    skip_permissions: bool,
+    #[serde(default)]
+    continue_from_last: bool,
+    timeout: Option<String>,
+    misfire: Option<String>,
+    misfire_grace: Option<String>,
+    max_attempts: Option<u32>,
+    retry_delay: Option<String>,
+    retry_backoff: Option<f64>,
+    #[serde(default)]
+    retry_on_exit: Vec<i32>,
+    #[serde(default)]
+    retry_on_output: Vec<String>,
+    #[serde(default)]
+    continue_after_limit: bool,
+    #[serde(default)]
+    depends_on: Vec<String>,
+    on_dependency_failure: Option<String>,
+    #[serde(default)]
+    if_new_commits: bool,
+    #[serde(default)]
+    if_file_exists: Vec<String>,
+    #[serde(default)]
+    if_command: Vec<String>,
+}
+
+async fn create_schedule(
+    State(db): State<Database>,
+    Json(request): Json<CreateScheduleRequest>,
+) -> ApiResult<(StatusCode, Json<Schedule>)> {
+    let schedule = build_schedule(&ScheduleOptions {
+        command: &request.command,
+        time: request.time.as_deref(),
+        date: request.date.as_deref().unwrap_or("today"),
+        cron: request.cron.as_deref(),
+        every: request.every.as_deref(),
+        until: request.until.as_deref(),
+        count: request.count,
+        mode: request.mode.as_deref().unwrap_or("claude"),
+        branch: request.branch.as_deref(),
+        worktree: request.worktree,
+        memo: request.memo.as_deref(),
+        execution_path: request.execution_path.as_deref(),
+        skip_permissions: request.skip_permissions,
+        continue_from_last: request.continue_from_last,
+        timeout: request.timeout.as_deref(),
+        misfire: request.misfire.as_deref(),
+        misfire_grace: request.misfire_grace.as_deref(),
+        max_attempts: request.max_attempts,
+        retry_delay: request.retry_delay.as_deref(),
+        retry_backoff: request.retry_backoff,
+        retry_on_exit: &request.retry_on_exit,
+        retry_on_output: &request.retry_on_output,
+        continue_after_limit: request.continue_after_limit,
+        depends_on: &request.depends_on,
+        on_dependency_failure: request.on_dependency_failure.as_deref(),
+        if_new_commits: request.if_new_commits,
+        if_file_exists: &request.if_file_exists,
+        if_command: &request.if_command,
+    })
+    .map_err(ApiError::bad_request)?;
+    check_dependencies(&db, &schedule)
+        .await
+        .map_err(ApiError::bad_request)?;
+
+    db.create_schedule(&schedule).await?;
+
+    Ok((StatusCode::CREATED, Json(schedule)))
+}
+
+/// Load a schedule for a request that changes it. Running schedules are left
+/// to the daemon, as with the CLI.
+async fn load_idle_schedule(db: &Database, id: &str) -> ApiResult<Schedule> {
+    let schedule = db
+        .get_schedule(id)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("Schedule '{id}' not found")))?;
+    if schedule.status == ScheduleStatus::Running {
+        return Err(ApiError::conflict(format!(
+            "Schedule '{id}' is running. Wait for it to finish first"
+        )));
+    }
+    Ok(schedule)
+}
+
+async fn delete_schedule(
+    State(db): State<Database>,
+    Path(id): Path<String>,
+) -> ApiResult<StatusCode> {
+    load_idle_schedule(&db, &id).await?;
+    if db.delete_schedule(&id).await? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::not_found(format!("Schedule '{id}' not found")))
+    }
+}
+
+/// Cancel a schedule so that it does not run anymore, like the `cancel` command
+async fn cancel_schedule(
+    State(db): State<Database>,
+    Path(id): Path<String>,
+) -> ApiResult<StatusCode> {
+    let schedule = load_idle_schedule(&db, &id).await?;
+    if schedule.status == ScheduleStatus::Cancelled {
+        return Err(ApiError::conflict(format!(
+            "Schedule '{id}' is already cancelled"
+        )));
+    }
+    if !db.cancel_schedule(&id).await? {
+        return Err(ApiError::conflict(format!(
+            "Schedule '{id}' started running and was not cancelled"
+        )));
+    }
+
+    info!("schedule cancelled: id={id}");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Start a schedule right away. The run goes through the worker pool in the
+/// background and the response carries the execution id to look up in the
+/// history.
+async fn run_schedule(
+    State(state): State<DaemonState>,
+    Path(id): Path<String>,
+) -> ApiResult<(StatusCode, Json<serde_json::Value>)> {
+    if state.shutdown.is_cancelled() {
+        return Err(ApiError {
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            message: "The daemon is shutting down".to_string(),
+        });
+    }
+
+    let schedule = state
+        .db
+        .get_schedule(&id)
+        .await?
+        .ok_or_else(|| ApiError::not_found(format!("Schedule '{id}' not found")))?;
+
+    let claim = state.workers.claim(&id).ok_or_else(|| {
+        ApiError::conflict(format!("Schedule '{id}' is already queued or running"))
+    })?;
+
+    let execution_id = daemon::new_execution_id();
+    info!("schedule run requested: id={id} execution_id={execution_id}");
+    daemon::dispatch(
+        &state,
+        claim,
+        schedule,
+        execution_id.clone(),
+        RunTrigger::Manual,
+    );
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(json!({ "schedule_id": id, "execution_id": execution_id })),
+    ))
+}
+
+#[derive(Deserialize)]
+struct HistoryQuery {
+    status: Option<String>,
+    #[serde(rename = "type")]
+    exec_type: Option<String>,
+    branch: Option<String>,
+    from: Option<NaiveDate>,
+    to: Option<NaiveDate>,
+    /// e.g. `10m`
+    min_duration: Option<String>,
+    exit_code: Option<i32>,
+    limit: Option<usize>,
+}
+
+async fn list_history(
+    State(db): State<Database>,
+    Query(query): Query<HistoryQuery>,
+) -> ApiResult<Json<Vec<ExecutionHistory>>> {
+    let min_duration = query
+        .min_duration
+        .as_deref()
+        .map(crate::cli_commands::parse_min_duration)
+        .transpose()
+        .map_err(ApiError::bad_request)?;
+    let history = db
+        .get_execution_history(&HistoryFilter {
+            status: query.status.as_deref().map(ExecutionStatus::from_string),
+            execution_type: query.exec_type.as_deref().map(ExecutionType::from_string),
+            branch: query.branch,
+            schedule_id: None,
+            from: query.from.map(crate::utils::start_of_day),
+            to: query.to.map(crate::utils::end_of_day),
+            min_duration,
+            exit_code: query.exit_code,
+            limit: query.limit,
+        })
+        .await?;
+
+    Ok(Json(history))
+}
+
+async fn list_running(State(outputs): State<LiveOutputs>) -> Json<Vec<RunningExecution>> {
+    Json(outputs.running())
+}
+
+/// Stop a running execution. It ends as `cancelled` in the history once the
+/// command has exited.
+async fn cancel_execution(
+    State(outputs): State<LiveOutputs>,
+    Path(id): Path<String>,
+) -> ApiResult<StatusCode> {
+    if outputs.cancel(&id) {
+        info!("execution cancel requested: execution_id={id}");
+        Ok(StatusCode::ACCEPTED)
+    } else {
+        Err(ApiError::not_found(format!(
+            "Execution '{id}' is not running"
+        )))
+    }
+}
+
+/// Follow the output of an execution as Server-Sent Events.
+///
+/// Each line is sent as a `stdout` or `stderr` event, followed by a single
+/// `end` event carrying the final status. Lines produced before the client
+/// connected are replayed first. A finished execution is replayed from its
+/// recorded lines, or from the history for runs without them, where stdout and
+/// stderr are no longer told apart.
+async fn stream_execution(
+    State(state): State<DaemonState>,
+    Path(id): Path<String>,
+) -> ApiResult<Sse<impl Stream<Item = std::result::Result<Event, Infallible>>>> {
+    let events: std::pin::Pin<Box<dyn Stream<Item = OutputEvent> + Send>> =
+        if let Some((backlog, receiver)) = state.outputs.subscribe(&id) {
+            // A subscriber that falls too far behind skips the lines it missed
+            let live = BroadcastStream::new(receiver).filter_map(|event| event.ok());
+            Box::pin(tokio_stream::iter(backlog).chain(live))
+        } else {
+            let history = state
+                .db
+                .get_execution(&id)
+                .await?
+                .ok_or_else(|| ApiError::not_found(format!("Execution '{id}' not found")))?;
+
+            let lines = state
+                .db
+                .get_output_lines(&id, &OutputFilter::default())
+                .await?;
+            let mut events: Vec<OutputEvent> = if lines.is_empty() {
+                history
+                    .output
+                    .lines()
+                    .map(|line| OutputEvent::Line {
+                        stream: OutputStream::Stdout,
+                        text: line.to_string(),
+                    })
+                    .collect()
+            } else {
+                lines
+                    .into_iter()
+                    .map(|line| OutputEvent::Line {
+                        stream: if line.stream == "stderr" {
+                            OutputStream::Stderr
+                        } else {
+                            OutputStream::Stdout
+                        },
+                        text: line.text,
+                    })
+                    .collect()
+            };
+            events.push(OutputEvent::Finished {
+                status: history.status,
+            });
+            Box::pin(tokio_stream::iter(events))
+        };
+
+    let stream = events.map(|event| {
+        Ok(match event {
+            OutputEvent::Line { stream, text } => {
+                Event::default().event(stream.as_str()).data(text)
+            }
+            OutputEvent::Finished { status } => {
+                Event::default().event("end").data(status.to_db_string())
+            }
+        })
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
@@ -0,0 +1,393 @@
+use anyhow::{bail, Context, Result};
+use chrono::Local;
+use comfy_table::{ContentArrangement, Table};
+use serde::Deserialize;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+
+use crate::cli_commands::{
+    execute_command_internal, parse_timeout, CommandOutcome, ExecutionControl,
+};
+use crate::cli_handlers::status_emoji;
+use crate::daemon::new_execution_id;
+use crate::database::Database;
+use crate::git;
+use crate::models::{ExecutionHistory, ExecutionStatus, ExecutionType};
+
+/// A prompt list read from a file.
+///
+/// A `.txt` file has one prompt per line (blank lines and lines starting
+/// with `#` are ignored). A `.toml` file can also set defaults for the batch
+/// and per-prompt overrides:
+///
+/// ```toml
+/// mode = "claude"
+/// parallel = 2
+/// worktree = true
+///
+/// [[prompts]]
+/// prompt = "add tests for the parser"
+/// branch = "parser-tests"
+///
+/// [[prompts]]
+/// prompt = "cargo fmt"
+/// mode = "shell"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatchFile {
+    pub mode: Option<String>,
+    pub parallel: Option<usize>,
+    #[serde(default)]
+    pub worktree: bool,
+    #[serde(default)]
+    pub skip_permissions: bool,
+    pub timeout: Option<String>,
+    #[serde(default)]
+    pub prompts: Vec<BatchPrompt>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatchPrompt {
+    pub prompt: String,
+    pub mode: Option<String>,
+    /// Run this prompt on the worktree of this branch
+    pub branch: Option<String>,
+}
+
+impl BatchFile {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+
+        let file = if path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("toml"))
+        {
+            toml::from_str(&content)
+                .with_context(|| format!("Invalid batch file {}", path.display()))?
+        } else {
+            Self {
+                prompts: content
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(|line| BatchPrompt {
+                        prompt: line.to_string(),
+                        mode: None,
+                        branch: None,
+                    })
+                    .collect(),
+                ..Default::default()
+            }
+        };
+
+        if file.prompts.is_empty() {
+            bail!("No prompts in {}", path.display());
+        }
+        Ok(file)
+    }
+}
+
+/// Options of the `batch` command. Unset options fall back to the batch file.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BatchOptions<'a> {
+    pub mode: Option<&'a str>,
+    pub parallel: Option<usize>,
+    pub worktree: bool,
+    pub branch_prefix: Option<&'a str>,
+    pub skip_permissions: bool,
+    pub timeout: Option<&'a str>,
+    pub stop_on_failure: bool,
+}
+
+struct BatchItem {
+    number: usize,
+    prompt: String,
+    is_shell_mode: bool,
+    branch: String,
+    execution_path: String,
+}
+
+struct BatchResult {
+    item: BatchItem,
+    /// `None` when the prompt was never started
+    history: Option<ExecutionHistory>,
+    duration: Duration,
+}
+
+/// Run every prompt of a batch file, print a summary and exit with 1 if any
+/// prompt did not succeed
+pub async fn run_batch(db: &Database, path: &Path, options: &BatchOptions<'_>) -> Result<()> {
+    let file = BatchFile::load(path)?;
+
+    let mode = options
+        .mode
+        .or(file.mode.as_deref())
+        .unwrap_or("claude")
+        .to_lowercase();
+    let parallel = options.parallel.or(file.parallel).unwrap_or(1);
+    if parallel == 0 {
+        bail!("--parallel must be at least 1");
+    }
+    let worktree = options.worktree || file.worktree;
+    let skip_permissions = options.skip_permissions || file.skip_permissions;
+    let timeout = options
+        .timeout
+        .or(file.timeout.as_deref())
+        .map(parse_timeout)
+        .transpose()?
+        .map(|seconds| Duration::from_secs(seconds.into()));
+
+    let batch_id = format!(
+        "batch_{}",
+        chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0)
+    );
+    let current_dir = std::env::current_dir()?.to_string_lossy().to_string();
+    let branch_prefix = options.branch_prefix.unwrap_or(&batch_id);
+
+    // Worktrees are created up front, git does not like concurrent `worktree add`
+    let total = file.prompts.len();
+    let mut items = Vec::with_capacity(total);
+    for (index, prompt) in file.prompts.into_iter().enumerate() {
+        let number = index + 1;
+        let (branch, execution_path) = match prompt.branch {
+            Some(branch) => {
+                let path = git::create_worktree(&current_dir, &branch)?;
+                (branch, path)
+            }
+            None if worktree => {
+                let branch = format!("{branch_prefix}-{number}");
+                let path = git::create_worktree(&current_dir, &branch)?;
+                (branch, path)
+            }
+            None => (git::get_current_branch(), current_dir.clone()),
+        };
+
+        items.push(BatchItem {
+            number,
+            is_shell_mode: prompt
+                .mode
+                .as_deref()
+                .unwrap_or(&mode)
+                .eq_ignore_ascii_case("shell"),
+            prompt: prompt.prompt,
+            branch,
+            execution_path,
+        });
+    }
+
+    println!("🚀 Batch {batch_id}: {total} prompts, {parallel} at a time");
+
+    // The commands run in their own process groups, so Ctrl-C has to be passed on
+    let interrupt = CancellationToken::new();
+    let ctrl_c = interrupt.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            ctrl_c.cancel();
+        }
+    });
+
+    let slots = Arc::new(Semaphore::new(parallel));
+    let failed = Arc::new(AtomicBool::new(false));
+    let mut tasks = JoinSet::new();
+    let mut results = Vec::with_capacity(total);
+
+    let mut pending = items.into_iter();
+    for item in pending.by_ref() {
+        let slot = tokio::select! {
+            slot = slots.clone().acquire_owned() => slot?,
+            _ = interrupt.cancelled() => {
+                results.push(not_run(item));
+                break;
+            }
+        };
+        if interrupt.is_cancelled() || (options.stop_on_failure && failed.load(Ordering::SeqCst)) {
+            results.push(not_run(item));
+            break;
+        }
+
+        let db = db.clone();
+        let batch_id = batch_id.clone();
+        let interrupt = interrupt.clone();
+        let failed = failed.clone();
+        tasks.spawn(async move {
+            let _slot = slot;
+            let result = run_item(
+                &db,
+                item,
+                &batch_id,
+                skip_permissions,
+                timeout,
+                &interrupt,
+                total,
+            )
+            .await;
+            if !matches!(&result, Ok(result) if is_success(result)) {
+                failed.store(true, Ordering::SeqCst);
+            }
+            result
+        });
+    }
+    results.extend(pending.map(not_run));
+
+    while let Some(result) = tasks.join_next().await {
+        results.push(result??);
+    }
+    results.sort_by_key(|result| result.item.number);
+
+    print_summary(&batch_id, &results);
+
+    if !results.iter().all(is_success) {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+async fn run_item(
+    db: &Database,
+    item: BatchItem,
+    batch_id: &str,
+    skip_permissions: bool,
+    timeout: Option<Duration>,
+    interrupt: &CancellationToken,
+    total: usize,
+) -> Result<BatchResult> {
+    let execution_id = new_execution_id();
+    let executed_at = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    println!("▶️  [{}/{total}] {}", item.number, item.prompt);
+
+    let outcome = execute_command_internal(
+        &item.prompt,
+        item.is_shell_mode,
+        &item.execution_path,
+        skip_permissions,
+        false,
+        ExecutionControl {
+            interrupt: Some(interrupt),
+            timeout,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap_or_else(|e| CommandOutcome::failed(&e));
+    let duration = outcome.duration;
+
+    let history = ExecutionHistory {
+        id: execution_id,
+        command: item.prompt.clone(),
+        executed_at,
+        execution_type: if item.is_shell_mode {
+            ExecutionType::ShellMode
+        } else {
+            ExecutionType::Manual
+        },
+        status: outcome.status,
+        output: outcome.output,
+        branch: item.branch.clone(),
+        execution_path: item.execution_path.clone(),
+        claude_skip_permissions: skip_permissions,
+        claude_continue_from_last: false,
+        schedule_id: None,
+        attempt: None,
+        exit_code: outcome.exit_code,
+        batch_id: Some(batch_id.to_string()),
+        stdout: outcome.stdout,
+        stderr: outcome.stderr,
+        finished_at: Some(Local::now().format("%Y-%m-%d %H:%M:%S").to_string()),
+        duration_ms: Some(duration.as_millis() as u64),
+    };
+    db.create_execution_history(&history).await?;
+
+    println!(
+        "{} [{}/{total}] {} ({:.1}s)",
+        status_emoji(&history.status),
+        item.number,
+        history.status,
+        duration.as_secs_f64()
+    );
+
+    Ok(BatchResult {
+        item,
+        history: Some(history),
+        duration,
+    })
+}
+
+fn not_run(item: BatchItem) -> BatchResult {
+    BatchResult {
+        item,
+        history: None,
+        duration: Duration::ZERO,
+    }
+}
+
+fn is_success(result: &BatchResult) -> bool {
+    result
+        .history
+        .as_ref()
+        .is_some_and(|history| history.status == ExecutionStatus::Success)
+}
+
+fn print_summary(batch_id: &str, results: &[BatchResult]) {
+    let mut table = Table::new();
+    table.set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(vec![
+        "#",
+        "Prompt",
+        "Branch",
+        "Status",
+        "Duration",
+        "Execution ID",
+    ]);
+
+    for result in results {
+        let prompt = if result.item.prompt.chars().count() > 40 {
+            format!(
+                "{}...",
+                result.item.prompt.chars().take(40).collect::<String>()
+            )
+        } else {
+            result.item.prompt.clone()
+        };
+        let status = result.history.as_ref().map(|history| &history.status);
+
+        table.add_row(vec![
+            result.item.number.to_string(),
+            prompt,
+            result.item.branch.clone(),
+            format!(
+                "{} {}",
+                // Prompts that never ran show like skipped runs
+                status.map_or("⏭️", status_emoji),
+                status.map_or_else(|| "not run".to_string(), ToString::to_string)
+            ),
+            format!("{:.1}s", result.duration.as_secs_f64()),
+            result
+                .history
+                .as_ref()
+                .map(|history| history.id.clone())
+                .unwrap_or_default(),
+        ]);
+    }
+
+    let succeeded = results.iter().filter(|result| is_success(result)).count();
+    let not_run = results
+        .iter()
+        .filter(|result| result.history.is_none())
+        .count();
+    let total_duration: Duration = results.iter().map(|result| result.duration).sum();
+
+    println!("\n{table}");
+    println!(
+        "Batch {batch_id}: {succeeded} succeeded, {} failed, {not_run} not run ({:.1}s of command time)",
+        results.len() - succeeded - not_run,
+        total_duration.as_secs_f64()
+    );
+}
@@ -14,6 +14,7 @@ pub struct Cli {
 }
 
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
 pub enum Commands {
     /// Execute a command immediately
     Exec {
@@ -39,6 +40,44 @@ pub enum Commands {
         /// Continue from last Claude session (-c)
         #[arg(short = 'c', long)]
         continue_from_last: bool,
+
+        /// Kill the command after this long (e.g. 30m, 2h, 90s)
+        #[arg(long)]
+        timeout: Option<String>,
+    },
+
+    /// Run every prompt of a file (one prompt per line, or a .toml batch file)
+    Batch {
+        /// Prompt file (.txt or .toml)
+        file: std::path::PathBuf,
+
+        /// Execution mode [claude|shell] [default: claude, or mode from the batch file]
+        #[arg(short, long)]
+        mode: Option<String>,
+
+        /// Number of prompts to run at the same time [default: 1]
+        #[arg(short, long)]
+        parallel: Option<usize>,
+
+        /// Run each prompt on its own Git worktree branch
+        #[arg(short, long)]
+        worktree: bool,
+
+        /// Prefix of the worktree branches, numbered per prompt [default: batch id]
+        #[arg(long)]
+        branch_prefix: Option<String>,
+
+        /// Skip permissions check (--dangerously-skip-permissions)
+        #[arg(long)]
+        skip_permissions: bool,
+
+        /// Kill each command after this long (e.g. 30m, 2h, 90s)
+        #[arg(long)]
+        timeout: Option<String>,
+
+        /// Do not start further prompts once one has failed
+        #[arg(long)]
+        stop_on_failure: bool,
     },
 
     /// Schedule a command for later execution
@@ -47,13 +86,29 @@ pub enum Commands {
         command: String,
 
         /// Execution time (HH:MM format)
-        #[arg(short, long)]
-        time: String,
+        #[arg(short, long, required_unless_present_any = ["cron", "every", "depends_on"])]
+        time: Option<String>,
 
         /// Execution date [today|tomorrow|YYYY-MM-DD]
         #[arg(short, long, default_value = "today")]
         date: String,
 
+        /// Repeat on a cron expression (e.g. "0 3 * * 1-5", "@daily")
+        #[arg(long, conflicts_with_all = ["time", "date", "every"])]
+        cron: Option<String>,
+
+        /// Repeat at a fixed interval (e.g. 45m, 2h, 1h30m)
+        #[arg(long)]
+        every: Option<String>,
+
+        /// Stop repeating after this time [HH:MM|YYYY-MM-DD|YYYY-MM-DDTHH:MM]
+        #[arg(long)]
+        until: Option<String>,
+
+        /// Stop repeating after N runs
+        #[arg(long)]
+        count: Option<u32>,
+
         /// Execution mode [claude|shell]
         #[arg(short, long, default_value = "claude")]
         mode: String,
@@ -77,11 +132,141 @@ pub enum Commands {
         /// Continue from last Claude session (-c)
         #[arg(long)]
         continue_from_last: bool,
+
+        /// Kill the command after this long (e.g. 30m, 2h, 90s)
+        #[arg(long)]
+        timeout: Option<String>,
+
+        /// What to do when the run is late [run_once|skip|run_all]
+        #[arg(long)]
+        misfire: Option<String>,
+
+        /// How late a run may start with --misfire skip (e.g. 10m, default 5m)
+        #[arg(long)]
+        misfire_grace: Option<String>,
+
+        /// Retry a failed run until it has been attempted N times
+        #[arg(long)]
+        max_attempts: Option<u32>,
+
+        /// Wait before the first retry (e.g. 30s, 5m, default 1m)
+        #[arg(long)]
+        retry_delay: Option<String>,
+
+        /// Multiply the wait by this factor after each retry (default 2.0)
+        #[arg(long)]
+        retry_backoff: Option<f64>,
+
+        /// Only retry these exit codes (e.g. 1,75)
+        #[arg(long, value_delimiter = ',')]
+        retry_on_exit: Vec<i32>,
+
+        /// Only retry when the output contains this text (repeatable)
+        #[arg(long)]
+        retry_on_output: Vec<String>,
+
+        /// Continue the Claude session (-c) when re-run after a usage limit
+        #[arg(long)]
+        continue_after_limit: bool,
+
+        /// Only run after this schedule has completed successfully (repeatable)
+        #[arg(long = "depends-on", value_name = "ID")]
+        depends_on: Vec<String>,
+
+        /// What to do when a dependency fails [skip|run|wait]
+        #[arg(long)]
+        on_dependency_failure: Option<String>,
+
+        /// Only run if there are new commits since the last run
+        #[arg(long)]
+        if_new_commits: bool,
+
+        /// Only run if this file exists, relative to the execution path (repeatable)
+        #[arg(long, value_name = "PATH")]
+        if_file_exists: Vec<String>,
+
+        /// Only run if this shell command exits 0 in the execution path (repeatable)
+        #[arg(long, value_name = "COMMAND")]
+        if_command: Vec<String>,
+    },
+
+    /// Cancel a schedule so that it does not run anymore
+    Cancel {
+        /// Schedule ID
+        id: String,
+    },
+
+    /// Delete a schedule
+    Delete {
+        /// Schedule ID
+        id: String,
+    },
+
+    /// Change a schedule that is not running
+    Edit {
+        /// Schedule ID
+        id: String,
+
+        /// New command
+        #[arg(long)]
+        command: Option<String>,
+
+        /// New execution time (HH:MM format)
+        #[arg(short, long)]
+        time: Option<String>,
+
+        /// Execution date for --time [today|tomorrow|YYYY-MM-DD]
+        #[arg(short, long, requires = "time")]
+        date: Option<String>,
+
+        /// Execution mode [claude|shell]
+        #[arg(short, long)]
+        mode: Option<String>,
+
+        /// Git worktree branch
+        #[arg(short, long)]
+        branch: Option<String>,
+
+        /// Memo
+        #[arg(long)]
+        memo: Option<String>,
+
+        /// Kill each run after this long (e.g. 30m, 2h, 90s), or `none`
+        #[arg(long)]
+        timeout: Option<String>,
+
+        /// Skip permissions check (--dangerously-skip-permissions)
+        #[arg(long, value_name = "BOOL")]
+        skip_permissions: Option<bool>,
+
+        /// Continue from last Claude session (-c)
+        #[arg(long, value_name = "BOOL")]
+        continue_from_last: Option<bool>,
+    },
+
+    /// Move a schedule to a new time and make it pending again
+    Reschedule {
+        /// Schedule ID
+        id: String,
+
+        /// Execution time (HH:MM format)
+        #[arg(short, long)]
+        time: String,
+
+        /// Execution date [today|tomorrow|YYYY-MM-DD]
+        #[arg(short, long, default_value = "today")]
+        date: String,
+    },
+
+    /// Stop a running execution (marked cancelled in the history)
+    Kill {
+        /// Execution ID (see `history` or GET /api/executions/running)
+        execution_id: String,
     },
 
     /// List scheduled commands
     List {
-        /// Filter by status [pending|completed|failed]
+        /// Filter by status [pending|running|rate_limited|completed|failed|cancelled]
         #[arg(short, long)]
         status: Option<String>,
 
@@ -96,7 +281,7 @@ pub enum Commands {
 
     /// Show execution history
     History {
-        /// Filter by status [success|failed]
+        /// Filter by status [success|failed|interrupted|timed_out|skipped|rate_limited|cancelled]
         #[arg(short, long)]
         status: Option<String>,
 
@@ -123,6 +308,71 @@ pub enum Commands {
         /// End date (YYYY-MM-DD)
         #[arg(long)]
         to: Option<NaiveDate>,
+
+        /// Only runs that took at least this long (e.g. 10m, 1h, 90s)
+        #[arg(long)]
+        min_duration: Option<String>,
+
+        /// Only runs that exited with this code
+        #[arg(long, allow_hyphen_values = true)]
+        exit_code: Option<i32>,
+    },
+
+    /// Search the commands and output of past executions
+    Search {
+        /// Words to search for; every word must appear (3 characters or more each)
+        query: String,
+
+        /// Output format [text|json]
+        #[arg(short, long, default_value = "text")]
+        format: String,
+
+        /// Limit number of results
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+    },
+
+    /// Show everything about a schedule or an execution
+    Show {
+        /// Schedule or execution ID, or a unique beginning of one
+        id: String,
+
+        /// Print only the output (of the latest run for a schedule), for piping
+        #[arg(long)]
+        output_only: bool,
+
+        /// Print directly instead of through $PAGER
+        #[arg(long)]
+        no_pager: bool,
+    },
+
+    /// Print the output of an execution, following it while it runs
+    Logs {
+        /// Execution ID, or a unique beginning of one
+        execution_id: String,
+
+        /// Keep printing new lines until the execution has finished
+        #[arg(short, long)]
+        follow: bool,
+
+        /// Only lines printed within this long (e.g. 10m) or since this time (e.g. "2024-01-01 10:00")
+        #[arg(long)]
+        since: Option<String>,
+
+        /// Only lines containing this text
+        #[arg(long)]
+        grep: Option<String>,
+
+        /// Only lines printed to stderr
+        #[arg(long)]
+        stderr: bool,
+    },
+
+    /// Show how schedules depend on each other
+    Graph {
+        /// Output format [text|dot]
+        #[arg(short, long, default_value = "text")]
+        format: String,
     },
 
     /// Run as a daemon process
@@ -131,9 +381,9 @@ pub enum Commands {
         #[arg(short, long, default_value = "8080")]
         port: u16,
 
-        /// Check interval in seconds
-        #[arg(short, long, default_value = "5")]
-        interval: u64,
+        /// Check interval in seconds [default: general.check_interval from the config file]
+        #[arg(short, long)]
+        interval: Option<u64>,
 
         /// PID file path
         #[arg(long)]
@@ -143,11 +393,29 @@ pub enum Commands {
         #[arg(long)]
         log_file: Option<String>,
 
+        /// Rotate the log file once it grows beyond this size (e.g. 10MB, 512KB, 0 to disable)
+        #[arg(long, default_value = "10MB")]
+        log_max_size: String,
+
+        /// Also rotate the log file by time [never|hourly|daily]
+        #[arg(long, default_value = "daily")]
+        log_rotate: String,
+
+        /// Number of rotated log files to keep
+        #[arg(long, default_value = "5")]
+        log_keep: usize,
+
         /// Run in background
         #[arg(short, long)]
         detach: bool,
     },
 
+    /// Manage the database
+    Db {
+        #[command(subcommand)]
+        action: DbAction,
+    },
+
     /// Manage configuration
     Config {
         #[command(subcommand)]
@@ -175,3 +443,13 @@ pub enum ConfigAction {
         key: String,
     },
 }
+
+#[derive(Subcommand)]
+pub enum DbAction {
+    /// Apply pending schema migrations (also done on every start)
+    Migrate {
+        /// Only show which migrations have been applied
+        #[arg(long)]
+        status: bool,
+    },
+}
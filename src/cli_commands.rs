@@ -1,13 +1,21 @@
 use anyhow::{Context, Result};
 use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime};
 use std::process::Stdio;
+use std::time::Duration;
 use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::Command;
+use tokio::process::{Child, Command};
+use tokio_util::sync::CancellationToken;
 
+use crate::cron::CronExpression;
 use crate::database::Database;
 use crate::git;
-use crate::models::{Schedule, ScheduleStatus};
+use crate::live_output::{OutputSink, OutputStream};
+use crate::models::{
+    DependencyFailurePolicy, ExecutionProcess, ExecutionStatus, MisfirePolicy, Schedule,
+    ScheduleCondition, ScheduleStatus,
+};
 
+#[allow(clippy::too_many_arguments)]
 pub async fn execute_command_immediate(
     command: &str,
     mode: &str,
@@ -16,7 +24,10 @@ pub async fn execute_command_immediate(
     verbose: bool,
     skip_permissions: bool,
     continue_from_last: bool,
+    timeout: Option<&str>,
 ) -> Result<()> {
+    let timeout = timeout.map(parse_timeout).transpose()?;
+
     let execution_path = if worktree {
         if let Some(branch_name) = branch {
             git::get_worktree_path(branch_name)?
@@ -33,38 +44,623 @@ pub async fn execute_command_immediate(
         println!("Path: {execution_path}");
     }
 
+    // The command runs in its own process group, so Ctrl-C has to be passed on
+    let interrupt = CancellationToken::new();
+    let ctrl_c = interrupt.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            ctrl_c.cancel();
+        }
+    });
+
     let is_shell_mode = mode.to_lowercase() == "shell";
-    let (success, output) = execute_command_internal(
+    let outcome = execute_command_internal(
         command,
         is_shell_mode,
         &execution_path,
         skip_permissions,
         continue_from_last,
+        ExecutionControl {
+            interrupt: Some(&interrupt),
+            timeout: timeout.map(|seconds| Duration::from_secs(seconds.into())),
+            ..Default::default()
+        },
     )
     .await?;
 
-    println!("\n{output}");
+    println!("\n{}", outcome.output);
 
-    if !success {
-        std::process::exit(1);
+    match outcome.status {
+        ExecutionStatus::Success => {}
+        ExecutionStatus::Interrupted => std::process::exit(130),
+        ExecutionStatus::TimedOut => std::process::exit(124),
+        _ => std::process::exit(1),
     }
 
     Ok(())
 }
 
-#[allow(clippy::too_many_arguments)]
-pub async fn schedule_command(
-    db: &Database,
-    command: &str,
-    time: &str,
-    date: &str,
-    mode: &str,
-    branch: Option<&str>,
-    worktree: bool,
-    memo: Option<&str>,
-    skip_permissions: bool,
-    continue_from_last: bool,
-) -> Result<()> {
+/// Options for creating a schedule, shared by the `schedule` command and the daemon API
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ScheduleOptions<'a> {
+    pub command: &'a str,
+    pub time: Option<&'a str>,
+    pub date: &'a str,
+    pub cron: Option<&'a str>,
+    pub every: Option<&'a str>,
+    pub until: Option<&'a str>,
+    pub count: Option<u32>,
+    pub mode: &'a str,
+    pub branch: Option<&'a str>,
+    pub worktree: bool,
+    pub memo: Option<&'a str>,
+    pub execution_path: Option<&'a str>,
+    pub skip_permissions: bool,
+    pub continue_from_last: bool,
+    pub timeout: Option<&'a str>,
+    pub misfire: Option<&'a str>,
+    pub misfire_grace: Option<&'a str>,
+    pub max_attempts: Option<u32>,
+    pub retry_delay: Option<&'a str>,
+    pub retry_backoff: Option<f64>,
+    pub retry_on_exit: &'a [i32],
+    pub retry_on_output: &'a [String],
+    pub continue_after_limit: bool,
+    pub depends_on: &'a [String],
+    pub on_dependency_failure: Option<&'a str>,
+    pub if_new_commits: bool,
+    pub if_file_exists: &'a [String],
+    pub if_command: &'a [String],
+}
+
+pub async fn schedule_command(db: &Database, options: &ScheduleOptions<'_>) -> Result<()> {
+    let schedule = build_schedule(options)?;
+    check_dependencies(db, &schedule).await?;
+    db.create_schedule(&schedule).await?;
+
+    println!("✅ Schedule created successfully!");
+    println!("  Command: {}", schedule.command);
+    println!(
+        "  Time: {}",
+        schedule.scheduled_time.as_deref().unwrap_or_default()
+    );
+    if schedule.is_recurring() {
+        println!("  Repeat: {}", schedule.repeat_description());
+    }
+    if let Some(max_runs) = schedule.max_runs {
+        println!("  Runs: {max_runs}");
+    }
+    if let Some(timeout) = schedule.timeout_seconds {
+        println!(
+            "  Timeout: {}",
+            crate::utils::format_duration(chrono::Duration::seconds(timeout.into()))
+        );
+    }
+    if let Some(max_attempts) = schedule.retry_max_attempts {
+        println!(
+            "  Retry: up to {max_attempts} attempts, first after {}",
+            crate::utils::format_duration(chrono::Duration::from_std(schedule.retry_delay(1))?)
+        );
+    }
+    if schedule.misfire_policy != MisfirePolicy::RunOnce {
+        println!("  Misfire: {}", schedule.misfire_policy.to_db_string());
+    }
+    if !schedule.depends_on.is_empty() {
+        println!(
+            "  After: {} (on failure: {})",
+            schedule.depends_on.join(", "),
+            schedule.on_dependency_failure.to_db_string()
+        );
+    }
+    if !schedule.conditions.is_empty() {
+        let conditions: Vec<String> = schedule
+            .conditions
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        println!("  Only if: {}", conditions.join(", "));
+    }
+    println!("  Mode: {}", options.mode);
+    println!("  Branch: {}", schedule.branch);
+
+    Ok(())
+}
+
+/// Validate schedule options and build a pending `Schedule`
+pub fn build_schedule(options: &ScheduleOptions<'_>) -> Result<Schedule> {
+    let interval_minutes = options
+        .every
+        .map(|every| -> Result<u32> {
+            let minutes = crate::utils::parse_duration(every)?.num_minutes();
+            if minutes < 1 {
+                anyhow::bail!("Repeat interval must be at least 1 minute");
+            }
+            u32::try_from(minutes)
+                .map_err(|_| anyhow::anyhow!("Repeat interval '{every}' is too long"))
+        })
+        .transpose()?;
+    let repeat_until = options.until.map(parse_until).transpose()?;
+    let timeout_seconds = options.timeout.map(parse_timeout).transpose()?;
+    let misfire_policy = options
+        .misfire
+        .map(parse_misfire_policy)
+        .transpose()?
+        .unwrap_or_default();
+    let misfire_grace_seconds = options.misfire_grace.map(parse_timeout).transpose()?;
+    let retry_delay_seconds = options.retry_delay.map(parse_timeout).transpose()?;
+    let on_dependency_failure = options
+        .on_dependency_failure
+        .map(parse_dependency_failure_policy)
+        .transpose()?;
+
+    if options.cron.is_some() && interval_minutes.is_some() {
+        anyhow::bail!("--cron and --every cannot be used together");
+    }
+    if options.cron.is_none()
+        && interval_minutes.is_none()
+        && (repeat_until.is_some() || options.count.is_some())
+    {
+        anyhow::bail!("--until and --count require --cron or --every");
+    }
+    if options.count == Some(0) {
+        anyhow::bail!("--count must be at least 1");
+    }
+    if misfire_grace_seconds.is_some() && misfire_policy != MisfirePolicy::Skip {
+        anyhow::bail!("--misfire-grace requires --misfire skip");
+    }
+    if options.max_attempts.is_none()
+        && (retry_delay_seconds.is_some()
+            || options.retry_backoff.is_some()
+            || !options.retry_on_exit.is_empty()
+            || !options.retry_on_output.is_empty())
+    {
+        anyhow::bail!("--retry-delay, --retry-backoff and --retry-on-* require --max-attempts");
+    }
+    if on_dependency_failure.is_some() && options.depends_on.is_empty() {
+        anyhow::bail!("--on-dependency-failure requires --depends-on");
+    }
+    if options.max_attempts == Some(0) {
+        anyhow::bail!("--max-attempts must be at least 1");
+    }
+    if options
+        .retry_backoff
+        .is_some_and(|backoff| !backoff.is_finite() || backoff < 1.0)
+    {
+        anyhow::bail!("--retry-backoff must be at least 1.0");
+    }
+
+    if options
+        .if_command
+        .iter()
+        .chain(options.if_file_exists)
+        .any(|value| value.trim().is_empty())
+    {
+        anyhow::bail!("--if-file-exists and --if-command cannot be empty");
+    }
+    let mut conditions = Vec::new();
+    if options.if_new_commits {
+        conditions.push(ScheduleCondition::NewCommits);
+    }
+    conditions.extend(
+        options
+            .if_file_exists
+            .iter()
+            .map(|path| ScheduleCondition::FileExists { path: path.clone() }),
+    );
+    conditions.extend(
+        options
+            .if_command
+            .iter()
+            .map(|command| ScheduleCondition::Command {
+                command: command.clone(),
+            }),
+    );
+
+    let scheduled_time_str = if let Some(expression) = options.cron {
+        // Recurring schedules start at the next occurrence of the expression
+        let cron_expression: CronExpression = expression.parse()?;
+        cron_expression
+            .next_after(Local::now().naive_local())
+            .ok_or_else(|| anyhow::anyhow!("Cron expression '{expression}' never fires"))?
+            .format("%Y-%m-%dT%H:%M")
+            .to_string()
+    } else if let Some(time) = options.time {
+        parse_schedule_time(time, options.date)?
+    } else if interval_minutes.is_some() || !options.depends_on.is_empty() {
+        // Interval schedules without --time start right away, dependent
+        // schedules as soon as their dependencies have succeeded
+        Local::now().format("%Y-%m-%dT%H:%M").to_string()
+    } else {
+        anyhow::bail!("Either --time, --cron, --every or --depends-on is required");
+    };
+
+    if let Some(until) = &repeat_until {
+        if scheduled_time_str > *until {
+            anyhow::bail!("--until ({until}) is before the first run ({scheduled_time_str})");
+        }
+    }
+
+    // Get execution branch
+    let execution_branch = if options.worktree {
+        if let Some(branch_name) = options.branch {
+            branch_name.to_string()
+        } else {
+            git::get_current_branch()
+        }
+    } else {
+        git::get_current_branch()
+    };
+
+    let execution_path = match options.execution_path {
+        Some(path) => crate::utils::expand_path(path),
+        None => std::env::current_dir()?.to_string_lossy().to_string(),
+    };
+
+    Ok(Schedule {
+        id: format!(
+            "schedule_{}",
+            chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0)
+        ),
+        command: options.command.to_string(),
+        scheduled_time: Some(scheduled_time_str),
+        _memo: options.memo.unwrap_or("").to_string(),
+        created_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        status: ScheduleStatus::Pending,
+        is_shell_mode: options.mode.to_lowercase() == "shell",
+        branch: execution_branch,
+        execution_path,
+        claude_skip_permissions: options.skip_permissions,
+        claude_continue_from_last: options.continue_from_last,
+        cron_expression: options.cron.map(|c| c.to_string()),
+        interval_minutes,
+        max_runs: options.count,
+        repeat_until,
+        run_count: 0,
+        timeout_seconds,
+        misfire_policy,
+        misfire_grace_seconds,
+        retry_max_attempts: options.max_attempts,
+        retry_delay_seconds,
+        retry_multiplier: options.retry_backoff,
+        retry_on_exit_codes: options.retry_on_exit.to_vec(),
+        retry_on_output: options.retry_on_output.to_vec(),
+        continue_after_limit: options.continue_after_limit,
+        depends_on: options.depends_on.to_vec(),
+        on_dependency_failure: on_dependency_failure.unwrap_or_default(),
+        conditions,
+        last_commit: None,
+        claimed_by: None,
+        started_at: None,
+    })
+}
+
+/// Parse `--timeout` into seconds
+pub fn parse_timeout(timeout: &str) -> Result<u32> {
+    let seconds = crate::utils::parse_duration(timeout)?.num_seconds();
+    if seconds < 1 {
+        anyhow::bail!("Timeout must be at least 1 second");
+    }
+    u32::try_from(seconds).map_err(|_| anyhow::anyhow!("Timeout '{timeout}' is too long"))
+}
+
+/// Parse `--min-duration` (e.g. `10m`)
+pub fn parse_min_duration(duration: &str) -> Result<Duration> {
+    let duration = crate::utils::parse_duration(duration)?;
+    Ok(duration.to_std()?)
+}
+
+/// Parse `--since`: a duration back from now (`10m`) or a time
+/// (`2024-01-01 10:00`, `2024-01-01`)
+pub fn parse_since(since: &str) -> Result<NaiveDateTime> {
+    let since = since.trim();
+    if let Ok(duration) = crate::utils::parse_duration(since) {
+        return Local::now()
+            .naive_local()
+            .checked_sub_signed(duration)
+            .ok_or_else(|| anyhow::anyhow!("--since '{since}' is too far back"));
+    }
+    for format in [
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+    ] {
+        if let Ok(time) = NaiveDateTime::parse_from_str(since, format) {
+            return Ok(time);
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(since, "%Y-%m-%d") {
+        return Ok(date.and_time(NaiveTime::MIN));
+    }
+    anyhow::bail!(
+        "Invalid time '{since}'. Use a duration such as 10m or a time such as \"2024-01-01 10:00\""
+    )
+}
+
+/// Parse `--misfire`, rejecting unknown policies instead of falling back to the default
+fn parse_misfire_policy(policy: &str) -> Result<MisfirePolicy> {
+    match policy.to_lowercase().replace('-', "_").as_str() {
+        "run_once" | "skip" | "run_all" => Ok(MisfirePolicy::from_string(policy)),
+        _ => anyhow::bail!("Invalid misfire policy '{policy}'. Use run_once, skip or run_all"),
+    }
+}
+
+/// Parse `--on-dependency-failure`
+fn parse_dependency_failure_policy(policy: &str) -> Result<DependencyFailurePolicy> {
+    match policy.to_lowercase().as_str() {
+        "skip" | "run" | "wait" => Ok(DependencyFailurePolicy::from_string(policy)),
+        _ => anyhow::bail!("Invalid dependency failure policy '{policy}'. Use skip, run or wait"),
+    }
+}
+
+/// Make sure every schedule a new schedule depends on exists
+pub async fn check_dependencies(db: &Database, schedule: &Schedule) -> Result<()> {
+    for parent in &schedule.depends_on {
+        if *parent == schedule.id {
+            anyhow::bail!("A schedule cannot depend on itself");
+        }
+        if db.get_schedule(parent).await?.is_none() {
+            anyhow::bail!("Schedule '{parent}' given in --depends-on not found");
+        }
+    }
+    Ok(())
+}
+
+/// Changes made by the `edit` command. Fields left as `None` are kept.
+#[derive(Default, PartialEq)]
+pub struct ScheduleEdit<'a> {
+    pub command: Option<&'a str>,
+    pub time: Option<&'a str>,
+    pub date: Option<&'a str>,
+    pub mode: Option<&'a str>,
+    pub branch: Option<&'a str>,
+    pub memo: Option<&'a str>,
+    /// `none` removes the timeout
+    pub timeout: Option<&'a str>,
+    pub skip_permissions: Option<bool>,
+    pub continue_from_last: Option<bool>,
+}
+
+/// Load a schedule for a command that changes it. Running schedules are left
+/// to the daemon.
+pub async fn load_idle_schedule(db: &Database, id: &str) -> Result<Schedule> {
+    let schedule = db
+        .get_schedule(id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Schedule '{id}' not found"))?;
+    if schedule.status == ScheduleStatus::Running {
+        anyhow::bail!("Schedule '{id}' is running. Wait for it to finish first");
+    }
+    Ok(schedule)
+}
+
+/// Point out the schedules still waiting on one that will not succeed anymore
+async fn warn_dependents(db: &Database, id: &str) -> Result<()> {
+    let dependents: Vec<String> = db
+        .get_schedules(None, None)
+        .await?
+        .into_iter()
+        .filter(|schedule| {
+            schedule.depends_on.iter().any(|parent| parent == id)
+                && matches!(
+                    schedule.status,
+                    ScheduleStatus::Pending | ScheduleStatus::RateLimited
+                )
+        })
+        .map(|schedule| schedule.id)
+        .collect();
+
+    if !dependents.is_empty() {
+        println!(
+            "⚠️ Depending schedules follow their --on-dependency-failure policy: {}",
+            dependents.join(", ")
+        );
+    }
+    Ok(())
+}
+
+pub async fn cancel_command(db: &Database, id: &str) -> Result<()> {
+    let schedule = load_idle_schedule(db, id).await?;
+    if schedule.status == ScheduleStatus::Cancelled {
+        anyhow::bail!("Schedule '{id}' is already cancelled");
+    }
+    if !db.cancel_schedule(id).await? {
+        anyhow::bail!("Schedule '{id}' started running and was not cancelled");
+    }
+
+    println!("✅ Schedule cancelled: {id}");
+    warn_dependents(db, id).await
+}
+
+pub async fn delete_command(db: &Database, id: &str) -> Result<()> {
+    load_idle_schedule(db, id).await?;
+    if !db.delete_schedule(id).await? {
+        anyhow::bail!("Schedule '{id}' not found");
+    }
+
+    println!("✅ Schedule deleted: {id}");
+    warn_dependents(db, id).await
+}
+
+pub async fn edit_command(db: &Database, id: &str, edit: &ScheduleEdit<'_>) -> Result<()> {
+    if *edit == ScheduleEdit::default() {
+        anyhow::bail!("Nothing to change. See `claude-scheduler edit --help`");
+    }
+    let mut schedule = load_idle_schedule(db, id).await?;
+
+    if let Some(command) = edit.command {
+        if command.trim().is_empty() {
+            anyhow::bail!("Command cannot be empty");
+        }
+        schedule.command = command.to_string();
+    }
+    if let Some(time) = edit.time {
+        let scheduled_time = parse_schedule_time(time, edit.date.unwrap_or("today"))?;
+        if let Some(until) = &schedule.repeat_until {
+            if scheduled_time > *until {
+                anyhow::bail!("The schedule repeats until {until}, before {scheduled_time}");
+            }
+        }
+        schedule.scheduled_time = Some(scheduled_time);
+    }
+    if let Some(mode) = edit.mode {
+        schedule.is_shell_mode = mode.to_lowercase() == "shell";
+    }
+    if let Some(branch) = edit.branch {
+        schedule.branch = branch.to_string();
+    }
+    if let Some(memo) = edit.memo {
+        schedule._memo = memo.to_string();
+    }
+    if let Some(timeout) = edit.timeout {
+        schedule.timeout_seconds = if timeout.eq_ignore_ascii_case("none") {
+            None
+        } else {
+            Some(parse_timeout(timeout)?)
+        };
+    }
+    if let Some(skip_permissions) = edit.skip_permissions {
+        schedule.claude_skip_permissions = skip_permissions;
+    }
+    if let Some(continue_from_last) = edit.continue_from_last {
+        schedule.claude_continue_from_last = continue_from_last;
+    }
+
+    if !db.update_schedule(&schedule).await? {
+        anyhow::bail!("Schedule '{id}' started running and was not changed");
+    }
+
+    println!("✅ Schedule updated: {id}");
+    println!("  Command: {}", schedule.command);
+    println!(
+        "  Time: {}",
+        schedule.scheduled_time.as_deref().unwrap_or_default()
+    );
+    println!(
+        "  Mode: {}",
+        if schedule.is_shell_mode {
+            "shell"
+        } else {
+            "claude"
+        }
+    );
+    println!("  Branch: {}", schedule.branch);
+    if schedule.status != ScheduleStatus::Pending {
+        println!(
+            "  Status: {} (use `reschedule` to run it again)",
+            schedule.status.to_db_string()
+        );
+    }
+
+    Ok(())
+}
+
+pub async fn reschedule_command(db: &Database, id: &str, time: &str, date: &str) -> Result<()> {
+    let schedule = load_idle_schedule(db, id).await?;
+    let scheduled_time = parse_schedule_time(time, date)?;
+
+    if !db.reschedule_schedule(id, &scheduled_time).await? {
+        anyhow::bail!("Schedule '{id}' started running and was not rescheduled");
+    }
+
+    println!("✅ Schedule rescheduled: {id}");
+    println!("  Time: {scheduled_time}");
+    if schedule.status != ScheduleStatus::Pending {
+        println!("  Status: {} -> pending", schedule.status.to_db_string());
+    }
+
+    Ok(())
+}
+
+/// Stop a running execution. The daemon running it is asked to stop it, and
+/// the command is signalled directly when that daemon is gone.
+pub async fn kill_command(db: &Database, execution_id: &str) -> Result<()> {
+    let Some(process) = db.get_execution_process(execution_id).await? else {
+        match db.get_execution(execution_id).await? {
+            Some(history) => anyhow::bail!(
+                "Execution '{execution_id}' has already finished ({})",
+                history.status.to_db_string()
+            ),
+            None => anyhow::bail!("Execution '{execution_id}' not found"),
+        }
+    };
+
+    let daemon_alive = process
+        .daemon_id
+        .strip_prefix("pid:")
+        .and_then(|pid| pid.parse().ok())
+        .is_some_and(|pid| crate::daemonize::is_process_alive(pid, false));
+    if !daemon_alive {
+        return kill_orphaned_execution(db, &process).await;
+    }
+
+    if !db.request_execution_cancel(execution_id).await? {
+        anyhow::bail!("Execution '{execution_id}' finished before it could be stopped");
+    }
+    println!("🛑 Stopping execution {execution_id}...");
+
+    // The daemon polls for requests every second and gives the command
+    // CANCEL_GRACE_PERIOD to exit
+    let deadline = tokio::time::Instant::now() + CANCEL_GRACE_PERIOD + Duration::from_secs(10);
+    while tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        if let Some(history) = db.get_execution(execution_id).await? {
+            println!(
+                "✅ Execution stopped: {execution_id} ({})",
+                history.status.to_db_string()
+            );
+            return Ok(());
+        }
+    }
+
+    println!("⏳ The daemon has not stopped it yet. Check `claude-scheduler history` later.");
+    Ok(())
+}
+
+/// Stop the command of an execution whose daemon is gone
+async fn kill_orphaned_execution(db: &Database, process: &ExecutionProcess) -> Result<()> {
+    let pid = process
+        .pid
+        .filter(|&pid| crate::daemonize::is_process_alive(pid, true));
+
+    let Some(pid) = pid else {
+        let reason = format!(
+            "Interrupted: its daemon ({}) stopped while it was running",
+            process.daemon_id
+        );
+        crate::daemon::record_orphaned_execution(
+            db,
+            process,
+            ExecutionStatus::Interrupted,
+            &reason,
+        )
+        .await?;
+        anyhow::bail!(
+            "Execution '{}' is not running anymore, its daemon ({}) is gone",
+            process.execution_id,
+            process.daemon_id
+        );
+    };
+
+    #[cfg(unix)]
+    {
+        terminate_process_group(pid)?;
+        let reason = format!("Cancelled: its daemon ({}) was gone", process.daemon_id);
+        crate::daemon::record_orphaned_execution(db, process, ExecutionStatus::Cancelled, &reason)
+            .await?;
+        println!(
+            "✅ Sent SIGTERM to execution {} (PID {pid}), its daemon ({}) is gone (cancelled)",
+            process.execution_id, process.daemon_id
+        );
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    anyhow::bail!("Stopping a command without its daemon is only supported on Unix (PID {pid})")
+}
+
+/// Parse `--time`/`--date` into the "YYYY-MM-DDTHH:MM" format stored in the database
+fn parse_schedule_time(time: &str, date: &str) -> Result<String> {
     // Parse date
     let target_date = match date.to_lowercase().as_str() {
         "today" => Local::now().date_naive(),
@@ -90,55 +686,91 @@ pub async fn schedule_command(
         NaiveTime::from_hms_opt(hour, minute, 0).ok_or_else(|| anyhow::anyhow!("Invalid time"))?;
 
     let scheduled_datetime = NaiveDateTime::new(target_date, target_time);
-    let scheduled_time_str = scheduled_datetime.format("%Y-%m-%dT%H:%M").to_string();
+    Ok(scheduled_datetime.format("%Y-%m-%dT%H:%M").to_string())
+}
 
-    // Get execution branch
-    let execution_branch = if worktree {
-        if let Some(branch_name) = branch {
-            branch_name.to_string()
+/// Parse `--until` into the "YYYY-MM-DDTHH:MM" format stored in the database
+fn parse_until(until: &str) -> Result<String> {
+    let until_datetime =
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(until, "%Y-%m-%dT%H:%M") {
+            datetime
+        } else if let Ok(datetime) = NaiveDateTime::parse_from_str(until, "%Y-%m-%d %H:%M") {
+            datetime
+        } else if let Ok(date) = NaiveDate::parse_from_str(until, "%Y-%m-%d") {
+            // A bare date repeats until the end of that day
+            date.and_hms_opt(23, 59, 0).unwrap()
+        } else if let Ok(time) = NaiveTime::parse_from_str(until, "%H:%M") {
+            Local::now().date_naive().and_time(time)
         } else {
-            git::get_current_branch()
-        }
-    } else {
-        git::get_current_branch()
-    };
+            anyhow::bail!("Invalid --until. Use HH:MM, YYYY-MM-DD or YYYY-MM-DDTHH:MM");
+        };
 
-    // Create schedule
-    let schedule = Schedule {
-        id: format!(
-            "schedule_{}",
-            chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0)
-        ),
-        command: command.to_string(),
-        scheduled_time: Some(scheduled_time_str.clone()),
-        _memo: memo.unwrap_or("").to_string(),
-        created_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
-        status: ScheduleStatus::Pending,
-        is_shell_mode: mode.to_lowercase() == "shell",
-        branch: execution_branch.clone(),
-        execution_path: std::env::current_dir()?.to_string_lossy().to_string(),
-        claude_skip_permissions: skip_permissions,
-        claude_continue_from_last: continue_from_last,
-    };
+    Ok(until_datetime.format("%Y-%m-%dT%H:%M").to_string())
+}
 
-    db.create_schedule(&schedule).await?;
+/// How long a cancelled command gets to exit after SIGTERM before it is killed
+pub const CANCEL_GRACE_PERIOD: Duration = Duration::from_secs(5);
 
-    println!("✅ Schedule created successfully!");
-    println!("  Command: {command}");
-    println!("  Time: {scheduled_time_str}");
-    println!("  Mode: {mode}");
-    println!("  Branch: {execution_branch}");
+/// Receives each line of a command with the pipe it came from
+pub type LineCallback = dyn Fn(OutputStream, &str) + Send + Sync;
 
-    Ok(())
+/// How a command run is observed and bounded
+#[derive(Default, Clone, Copy)]
+pub struct ExecutionControl<'a> {
+    /// Every line is published here as soon as it is read
+    pub live_output: Option<&'a OutputSink>,
+    /// Called with every line as soon as it is read
+    pub on_line: Option<&'a LineCallback>,
+    /// Called with the PID of the command once it has been started
+    pub on_spawn: Option<&'a (dyn Fn(u32) + Send + Sync)>,
+    /// Cancelling this kills the command, which is reported as interrupted
+    pub interrupt: Option<&'a CancellationToken>,
+    /// Cancelling this stops the command with SIGTERM, and SIGKILL after
+    /// `CANCEL_GRACE_PERIOD`. The run is reported as cancelled and keeps the
+    /// output printed until then.
+    pub cancel: Option<&'a CancellationToken>,
+    /// The command is killed and reported as timed out after this long
+    pub timeout: Option<Duration>,
 }
 
+/// Result of running a command to completion
+pub struct CommandOutcome {
+    pub status: ExecutionStatus,
+    /// stdout and stderr interleaved in the order the lines arrived
+    pub output: String,
+    pub stdout: String,
+    /// Also holds the notes about stopping the command ("Timed out after 1h")
+    pub stderr: String,
+    /// `None` when the command was killed
+    pub exit_code: Option<i32>,
+    /// From starting the command until it exited
+    pub duration: Duration,
+}
+
+impl CommandOutcome {
+    /// A run whose command could not be started
+    pub fn failed(error: &anyhow::Error) -> Self {
+        let message = format!("Error: {error}");
+        Self {
+            status: ExecutionStatus::Failed,
+            output: message.clone(),
+            stdout: String::new(),
+            stderr: message,
+            exit_code: None,
+            duration: Duration::ZERO,
+        }
+    }
+}
+
+/// Run a command to completion and return its status and output
 pub async fn execute_command_internal(
     command: &str,
     is_shell_mode: bool,
     execution_path: &str,
     skip_permissions: bool,
     continue_from_last: bool,
-) -> Result<(bool, String)> {
+    control: ExecutionControl<'_>,
+) -> Result<CommandOutcome> {
     let mut cmd = if is_shell_mode {
         if cfg!(target_os = "windows") {
             let mut cmd = Command::new("cmd");
@@ -162,10 +794,20 @@ pub async fn execute_command_internal(
     };
 
     cmd.current_dir(execution_path)
+        .stdin(Stdio::null())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped());
 
+    // Run in a new process group so that a kill also reaches everything the
+    // command started (claude's subprocesses, pipelines in shell mode)
+    #[cfg(unix)]
+    cmd.process_group(0);
+
+    let started = tokio::time::Instant::now();
     let mut child = cmd.spawn()?;
+    if let (Some(on_spawn), Some(pid)) = (control.on_spawn, child.id()) {
+        on_spawn(pid);
+    }
 
     let stdout = child.stdout.take().unwrap();
     let stderr = child.stderr.take().unwrap();
@@ -174,38 +816,184 @@ pub async fn execute_command_internal(
     let mut stderr_reader = BufReader::new(stderr).lines();
 
     let mut output = String::new();
+    let mut stdout_output = String::new();
+    let mut stderr_output = String::new();
+    let mut stdout_done = false;
+    let mut stderr_done = false;
+    let mut killed_status = None;
+    let mut exit_status = None;
+
+    let interrupt_requested = async {
+        match control.interrupt {
+            Some(token) => token.cancelled().await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::pin!(interrupt_requested);
 
-    // Read stdout and stderr
+    let timed_out = async {
+        match control.timeout {
+            Some(timeout) => tokio::time::sleep(timeout).await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::pin!(timed_out);
+
+    let cancel_requested = async {
+        match control.cancel {
+            Some(token) => token.cancelled().await,
+            None => std::future::pending().await,
+        }
+    };
+    tokio::pin!(cancel_requested);
+    let grace_period_over = tokio::time::sleep(CANCEL_GRACE_PERIOD);
+    tokio::pin!(grace_period_over);
+
+    let mut push_line = |stream: OutputStream, line: &str| {
+        for buffer in [
+            &mut output,
+            match stream {
+                OutputStream::Stdout => &mut stdout_output,
+                OutputStream::Stderr => &mut stderr_output,
+            },
+        ] {
+            buffer.push_str(line);
+            buffer.push('\n');
+        }
+        if let Some(sink) = control.live_output {
+            sink.line(stream, line);
+        }
+        if let Some(on_line) = control.on_line {
+            on_line(stream, line);
+        }
+    };
+
+    // Read stdout and stderr until both are closed, then wait for the command
+    // to exit. A command can close both long before it exits, so the timeout
+    // and the stop requests keep applying until it has.
     loop {
         tokio::select! {
-            result = stdout_reader.next_line() => {
+            result = stdout_reader.next_line(), if !stdout_done => {
                 match result {
-                    Ok(Some(line)) => {
-                        output.push_str(&line);
-                        output.push('\n');
-                    }
-                    Ok(None) => break,
+                    Ok(Some(line)) => push_line(OutputStream::Stdout, &line),
+                    Ok(None) => stdout_done = true,
                     Err(e) => {
-                        output.push_str(&format!("Error reading stdout: {e}\n"));
-                        break;
+                        push_line(OutputStream::Stdout, &format!("Error reading stdout: {e}"));
+                        stdout_done = true;
                     }
                 }
             }
-            result = stderr_reader.next_line() => {
+            result = stderr_reader.next_line(), if !stderr_done => {
                 match result {
-                    Ok(Some(line)) => {
-                        output.push_str(&line);
-                        output.push('\n');
-                    }
-                    Ok(None) => {},
+                    Ok(Some(line)) => push_line(OutputStream::Stderr, &line),
+                    Ok(None) => stderr_done = true,
                     Err(e) => {
-                        output.push_str(&format!("Error reading stderr: {e}\n"));
+                        push_line(OutputStream::Stderr, &format!("Error reading stderr: {e}"));
+                        stderr_done = true;
                     }
                 }
             }
+            result = child.wait(), if stdout_done && stderr_done => {
+                exit_status = Some(result?);
+                break;
+            }
+            _ = &mut interrupt_requested => {
+                push_line(OutputStream::Stderr, "Interrupted");
+                kill_process_tree(&mut child)?;
+                killed_status.get_or_insert(ExecutionStatus::Interrupted);
+                break;
+            }
+            _ = &mut cancel_requested, if killed_status.is_none() => {
+                // Keep reading, so that whatever the command prints while
+                // it shuts down is not lost
+                push_line(OutputStream::Stderr, "Cancelled");
+                terminate_process_tree(&mut child)?;
+                killed_status = Some(ExecutionStatus::Cancelled);
+                grace_period_over
+                    .as_mut()
+                    .reset(tokio::time::Instant::now() + CANCEL_GRACE_PERIOD);
+            }
+            _ = &mut grace_period_over, if killed_status == Some(ExecutionStatus::Cancelled) => {
+                push_line(
+                    OutputStream::Stderr,
+                    &format!(
+                        "Killed after not exiting within {}s",
+                        CANCEL_GRACE_PERIOD.as_secs()
+                    ),
+                );
+                kill_process_tree(&mut child)?;
+                break;
+            }
+            _ = &mut timed_out, if killed_status.is_none() => {
+                let timeout = control.timeout.unwrap_or_default();
+                push_line(
+                    OutputStream::Stderr,
+                    &format!(
+                        "Timed out after {}",
+                        crate::utils::format_duration(chrono::Duration::from_std(timeout)?)
+                    ),
+                );
+                kill_process_tree(&mut child)?;
+                killed_status = Some(ExecutionStatus::TimedOut);
+                break;
+            }
         }
     }
 
-    let status = child.wait().await?;
-    Ok((status.success(), output))
+    // A killed command exits right away
+    let exit_status = match exit_status {
+        Some(exit_status) => exit_status,
+        None => child.wait().await?,
+    };
+    let status = match killed_status {
+        Some(status) => status,
+        None if exit_status.success() => ExecutionStatus::Success,
+        None => ExecutionStatus::Failed,
+    };
+    Ok(CommandOutcome {
+        status,
+        output,
+        stdout: stdout_output,
+        stderr: stderr_output,
+        exit_code: exit_status.code(),
+        duration: started.elapsed(),
+    })
+}
+
+/// Ask a command and everything it started to exit
+fn terminate_process_tree(child: &mut Child) -> Result<()> {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        terminate_process_group(pid)?;
+        return Ok(());
+    }
+
+    child.start_kill()?;
+    Ok(())
+}
+
+/// Send SIGTERM to the process group led by `pid`
+#[cfg(unix)]
+pub fn terminate_process_group(pid: u32) -> Result<()> {
+    use nix::sys::signal::{killpg, Signal};
+    use nix::unistd::Pid;
+
+    killpg(Pid::from_raw(pid as i32), Signal::SIGTERM)?;
+    Ok(())
+}
+
+/// Kill a command together with everything it started
+fn kill_process_tree(child: &mut Child) -> Result<()> {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        use nix::sys::signal::{killpg, Signal};
+        use nix::unistd::Pid;
+
+        // The child leads its own process group (see `process_group(0)`)
+        killpg(Pid::from_raw(pid as i32), Signal::SIGKILL)?;
+        return Ok(());
+    }
+
+    child.start_kill()?;
+    Ok(())
 }
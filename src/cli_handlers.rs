@@ -1,10 +1,17 @@
-use anyhow::Result;
-use chrono::{Local, NaiveDate, NaiveDateTime};
+use anyhow::{anyhow, bail, Result};
+use chrono::NaiveDate;
 use comfy_table::{ContentArrangement, Table};
-
-use crate::database::Database;
-use crate::git;
-use crate::models::{ExecutionHistory, ExecutionStatus, ExecutionType, ScheduleStatus};
+use log::{error, info};
+use std::collections::{HashMap, HashSet};
+use std::io::{IsTerminal, Write};
+
+use crate::config::Config;
+use crate::database::{Database, HistoryFilter, OutputFilter};
+use crate::live_output::OutputStream;
+use crate::models::{
+    DependencyFailurePolicy, ExecutionHistory, ExecutionStatus, ExecutionType, Schedule,
+    ScheduleStatus,
+};
 
 pub async fn list_schedules(
     db: &Database,
@@ -29,10 +36,15 @@ pub async fn list_schedules(
                 "Status",
                 "Mode",
                 "Branch",
+                "Repeat",
+                "Remaining",
                 "Created At",
             ])?;
 
             for schedule in schedules {
+                let repeat = schedule.repeat_description();
+                let remaining = remaining_runs(&schedule);
+
                 wtr.write_record([
                     &schedule.id,
                     &schedule.command,
@@ -44,6 +56,8 @@ pub async fn list_schedules(
                         "claude".to_string()
                     },
                     &schedule.branch,
+                    &repeat,
+                    &remaining,
                     &schedule.created_at,
                 ])?;
             }
@@ -61,6 +75,8 @@ pub async fn list_schedules(
                 "Status",
                 "Mode",
                 "Branch",
+                "Repeat",
+                "Remaining",
             ]);
 
             for schedule in schedules {
@@ -76,6 +92,9 @@ pub async fn list_schedules(
                     schedule.command.clone()
                 };
 
+                let repeat = schedule.repeat_description();
+                let remaining = remaining_runs(&schedule);
+
                 table.add_row(vec![
                     id_short,
                     command_short,
@@ -87,6 +106,8 @@ pub async fn list_schedules(
                         "claude".to_string()
                     },
                     schedule.branch,
+                    repeat,
+                    remaining,
                 ]);
             }
 
@@ -97,6 +118,15 @@ pub async fn list_schedules(
     Ok(())
 }
 
+/// Remaining runs for the `list` output ("∞" for unlimited recurring schedules)
+fn remaining_runs(schedule: &Schedule) -> String {
+    match schedule.remaining_runs() {
+        Some(remaining) => remaining.to_string(),
+        None if schedule.is_recurring() => "∞".to_string(),
+        None => String::new(),
+    }
+}
+
 #[allow(clippy::too_many_arguments)]
 pub async fn show_history(
     db: &Database,
@@ -107,33 +137,23 @@ pub async fn show_history(
     limit: Option<usize>,
     from_date: Option<NaiveDate>,
     to_date: Option<NaiveDate>,
+    min_duration: Option<&str>,
+    exit_code: Option<i32>,
 ) -> Result<()> {
-    let status = status_filter.map(ExecutionStatus::from_string);
-    let exec_type = type_filter.map(ExecutionType::from_string);
-
-    let from = from_date.map(|d| {
-        d.and_hms_opt(0, 0, 0)
-            .unwrap()
-            .and_local_timezone(Local)
-            .unwrap()
-    });
-    let to = to_date.map(|d| {
-        d.and_hms_opt(23, 59, 59)
-            .unwrap()
-            .and_local_timezone(Local)
-            .unwrap()
-    });
-
-    let history = db
-        .get_execution_history(
-            status,
-            exec_type,
-            branch_filter.map(|s| s.to_string()),
-            from,
-            to,
-            limit,
-        )
-        .await?;
+    let filter = HistoryFilter {
+        status: status_filter.map(ExecutionStatus::from_string),
+        execution_type: type_filter.map(ExecutionType::from_string),
+        branch: branch_filter.map(|s| s.to_string()),
+        schedule_id: None,
+        from: from_date.map(crate::utils::start_of_day),
+        to: to_date.map(crate::utils::end_of_day),
+        min_duration: min_duration
+            .map(crate::cli_commands::parse_min_duration)
+            .transpose()?,
+        exit_code,
+        limit,
+    };
+    let history = db.get_execution_history(&filter).await?;
 
     match format {
         "json" => {
@@ -150,6 +170,11 @@ pub async fn show_history(
                 "Status",
                 "Branch",
                 "Output",
+                "Exit Code",
+                "Finished At",
+                "Duration (ms)",
+                "Stdout",
+                "Stderr",
             ])?;
 
             for entry in history {
@@ -161,6 +186,17 @@ pub async fn show_history(
                     &entry.status.to_db_string(),
                     &entry.branch,
                     &entry.output,
+                    &entry
+                        .exit_code
+                        .map(|code| code.to_string())
+                        .unwrap_or_default(),
+                    entry.finished_at.as_deref().unwrap_or_default(),
+                    &entry
+                        .duration_ms
+                        .map(|ms| ms.to_string())
+                        .unwrap_or_default(),
+                    &entry.stdout,
+                    &entry.stderr,
                 ])?;
             }
 
@@ -170,7 +206,9 @@ pub async fn show_history(
             // Table format
             let mut table = Table::new();
             table.set_content_arrangement(ContentArrangement::Dynamic);
-            table.set_header(vec!["Time", "Command", "Type", "Status", "Branch"]);
+            table.set_header(vec![
+                "Time", "Command", "Type", "Status", "Exit", "Duration", "Branch",
+            ]);
 
             for entry in history {
                 let command_short = if entry.command.len() > 40 {
@@ -179,16 +217,15 @@ pub async fn show_history(
                     entry.command.clone()
                 };
 
-                let status_emoji = match entry.status {
-                    ExecutionStatus::Success => "✅",
-                    ExecutionStatus::Failed => "❌",
-                };
-
                 table.add_row(vec![
                     entry.executed_at,
                     command_short,
                     entry.execution_type.to_db_string(),
-                    format!("{} {}", status_emoji, entry.status),
+                    format!("{} {}", status_emoji(&entry.status), entry.status),
+                    entry
+                        .exit_code
+                        .map_or_else(|| "-".to_string(), |code| code.to_string()),
+                    format_run_duration(entry.duration_ms),
                     entry.branch,
                 ]);
             }
@@ -200,114 +237,771 @@ pub async fn show_history(
     Ok(())
 }
 
-pub async fn run_daemon(
+/// Show short runs to a tenth of a second and longer ones as "1h30m"
+fn format_run_duration(duration_ms: Option<u64>) -> String {
+    match duration_ms {
+        None => "-".to_string(),
+        Some(ms) if ms < 60_000 => format!("{:.1}s", ms as f64 / 1000.0),
+        Some(ms) => crate::utils::format_duration(chrono::Duration::milliseconds(ms as i64)),
+    }
+}
+
+/// Emoji shown next to an execution status in tables and progress lines
+pub fn status_emoji(status: &ExecutionStatus) -> &'static str {
+    match status {
+        ExecutionStatus::Success => "✅",
+        ExecutionStatus::Failed => "❌",
+        ExecutionStatus::Interrupted => "⏹️",
+        ExecutionStatus::TimedOut => "⏱️",
+        ExecutionStatus::Skipped => "⏭️",
+        ExecutionStatus::RateLimited => "🚦",
+        ExecutionStatus::Cancelled => "🛑",
+    }
+}
+
+/// Print the executions whose command or output contains every search term,
+/// with the matching part of the output
+pub async fn search_history(db: &Database, query: &str, format: &str, limit: usize) -> Result<()> {
+    // Highlight with colors on a terminal, and with markers that survive
+    // being piped or saved otherwise
+    let highlight = if format != "json" && std::io::stdout().is_terminal() {
+        ("\x1b[1;33m", "\x1b[0m")
+    } else {
+        ("**", "**")
+    };
+    let hits = db.search_execution_history(query, limit, highlight).await?;
+
+    if format == "json" {
+        println!("{}", serde_json::to_string_pretty(&hits)?);
+        return Ok(());
+    }
+
+    if hits.is_empty() {
+        println!("No executions found for \"{query}\"");
+        return Ok(());
+    }
+
+    for hit in hits {
+        let entry = hit.history;
+        println!(
+            "{} {}  {}  🌿 {}  ({})",
+            status_emoji(&entry.status),
+            entry.executed_at,
+            entry.id,
+            entry.branch,
+            entry.status
+        );
+        println!("   💬 {}", entry.command);
+        println!(
+            "   {}",
+            hit.snippet.split_whitespace().collect::<Vec<_>>().join(" ")
+        );
+        println!();
+    }
+
+    Ok(())
+}
+
+/// How many IDs an ambiguous prefix lists
+const MAX_CANDIDATES: usize = 10;
+
+/// How many runs of a schedule `show` lists
+const RECENT_RUNS: usize = 5;
+
+/// How often `logs --follow` looks for new lines
+const LOGS_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// A schedule or an execution found by its ID
+enum Entry {
+    Schedule(Box<Schedule>),
+    Execution(Box<ExecutionHistory>),
+}
+
+/// Find the schedule or execution with this ID, or the only one whose ID
+/// starts with it
+async fn find_entry(db: &Database, id: &str) -> Result<Entry> {
+    if let Some(schedule) = db.get_schedule(id).await? {
+        return Ok(Entry::Schedule(Box::new(schedule)));
+    }
+    if let Some(execution) = db.get_execution(id).await? {
+        return Ok(Entry::Execution(Box::new(execution)));
+    }
+
+    let mut schedules = db.find_schedules_by_prefix(id, MAX_CANDIDATES).await?;
+    let mut executions = db.find_executions_by_prefix(id, MAX_CANDIDATES).await?;
+    match (schedules.len(), executions.len()) {
+        (0, 0) => bail!("No schedule or execution matches '{id}'"),
+        (1, 0) => Ok(Entry::Schedule(Box::new(schedules.remove(0)))),
+        (0, 1) => Ok(Entry::Execution(Box::new(executions.remove(0)))),
+        _ => Err(ambiguous_id(
+            id,
+            schedules
+                .iter()
+                .map(|schedule| schedule.id.as_str())
+                .chain(executions.iter().map(|execution| execution.id.as_str())),
+        )),
+    }
+}
+
+/// Find the finished or running execution with this ID, or the only one
+/// whose ID starts with it
+async fn find_execution_id(db: &Database, id: &str) -> Result<String> {
+    if db.get_execution(id).await?.is_some() || db.get_execution_process(id).await?.is_some() {
+        return Ok(id.to_string());
+    }
+
+    let mut candidates: Vec<String> = db
+        .find_executions_by_prefix(id, MAX_CANDIDATES)
+        .await?
+        .into_iter()
+        .map(|execution| execution.id)
+        .collect();
+    for process in db.get_execution_processes().await? {
+        if process.execution_id.starts_with(id) && !candidates.contains(&process.execution_id) {
+            candidates.push(process.execution_id);
+        }
+    }
+
+    match candidates.len() {
+        0 => bail!("No execution matches '{id}'"),
+        1 => Ok(candidates.remove(0)),
+        _ => Err(ambiguous_id(id, candidates.iter().map(String::as_str))),
+    }
+}
+
+fn ambiguous_id<'a>(id: &str, candidates: impl Iterator<Item = &'a str>) -> anyhow::Error {
+    let candidates: Vec<String> = candidates
+        .take(MAX_CANDIDATES)
+        .map(|candidate| format!("  {candidate}"))
+        .collect();
+    anyhow!(
+        "'{id}' is ambiguous. It is the beginning of:\n{}",
+        candidates.join("\n")
+    )
+}
+
+/// Print everything about a schedule or an execution, through a pager on a
+/// terminal. With `output_only` just the output is printed, for a schedule
+/// that of its latest run.
+pub async fn show_entry(db: &Database, id: &str, output_only: bool, no_pager: bool) -> Result<()> {
+    let entry = find_entry(db, id).await?;
+
+    if output_only {
+        let execution = match entry {
+            Entry::Execution(execution) => *execution,
+            Entry::Schedule(schedule) => recent_runs(db, &schedule.id, 1)
+                .await?
+                .pop()
+                .ok_or_else(|| anyhow!("Schedule {} has not run yet", schedule.id))?,
+        };
+        return write_stdout(&execution.output);
+    }
+
+    let text = match entry {
+        Entry::Schedule(schedule) => describe_schedule(
+            &schedule,
+            &recent_runs(db, &schedule.id, RECENT_RUNS).await?,
+        ),
+        Entry::Execution(execution) => describe_execution(&execution),
+    };
+
+    if no_pager {
+        write_stdout(&text)
+    } else {
+        page(&text)
+    }
+}
+
+async fn recent_runs(
     db: &Database,
-    port: u16,
-    interval: u64,
-    pid_file: Option<&str>,
-    log_file: Option<&str>,
-    _detach: bool,
-) -> Result<()> {
-    use std::fs::File;
-    use std::io::Write;
-    use tokio::time::{interval as tokio_interval, Duration};
+    schedule_id: &str,
+    limit: usize,
+) -> Result<Vec<ExecutionHistory>> {
+    db.get_execution_history(&HistoryFilter {
+        schedule_id: Some(schedule_id.to_string()),
+        limit: Some(limit),
+        ..Default::default()
+    })
+    .await
+}
 
-    // Write PID file if requested
-    if let Some(pid_path) = pid_file {
-        let pid = std::process::id();
-        let mut file = File::create(pid_path)?;
-        writeln!(file, "{pid}")?;
+fn describe_schedule(schedule: &Schedule, runs: &[ExecutionHistory]) -> String {
+    let mut text = String::new();
+    field(&mut text, "Schedule", &schedule.id);
+    field(&mut text, "Status", &schedule.status);
+    field(
+        &mut text,
+        "Mode",
+        if schedule.is_shell_mode {
+            "shell"
+        } else {
+            "claude"
+        },
+    );
+    field(&mut text, "Created", &schedule.created_at);
+    if let Some(scheduled_time) = &schedule.scheduled_time {
+        field(&mut text, "Next run", scheduled_time);
+    }
+    if schedule.is_recurring() {
+        field(&mut text, "Repeat", schedule.repeat_description());
+    }
+    let runs_description = match remaining_runs(schedule).as_str() {
+        "" => schedule.run_count.to_string(),
+        remaining => format!("{} ({remaining} remaining)", schedule.run_count),
+    };
+    field(&mut text, "Runs", runs_description);
+    if let Some(seconds) = schedule.timeout_seconds {
+        field(
+            &mut text,
+            "Timeout",
+            crate::utils::format_duration(chrono::Duration::seconds(seconds.into())),
+        );
+    }
+    field(&mut text, "Misfire", schedule.misfire_policy.to_db_string());
+    if let Some(attempts) = schedule.retry_max_attempts {
+        let mut retry = format!("up to {attempts} attempts");
+        if let Some(delay) = schedule.retry_delay_seconds {
+            retry.push_str(&format!(
+                ", {} apart",
+                crate::utils::format_duration(chrono::Duration::seconds(delay.into()))
+            ));
+        }
+        if let Some(multiplier) = schedule.retry_multiplier {
+            retry.push_str(&format!(" (x{multiplier})"));
+        }
+        if !schedule.retry_on_exit_codes.is_empty() {
+            retry.push_str(&format!(
+                ", on exit codes {:?}",
+                schedule.retry_on_exit_codes
+            ));
+        }
+        if !schedule.retry_on_output.is_empty() {
+            retry.push_str(&format!(", on output {:?}", schedule.retry_on_output));
+        }
+        field(&mut text, "Retry", retry);
+    }
+    if !schedule.depends_on.is_empty() {
+        field(
+            &mut text,
+            "Depends on",
+            format!(
+                "{} (on failure: {})",
+                schedule.depends_on.join(", "),
+                schedule.on_dependency_failure.to_db_string()
+            ),
+        );
+    }
+    for condition in &schedule.conditions {
+        field(&mut text, "Condition", condition);
+    }
+    field(&mut text, "Branch", &schedule.branch);
+    field(&mut text, "Path", &schedule.execution_path);
+    let options = claude_options(
+        schedule.claude_skip_permissions,
+        schedule.claude_continue_from_last,
+    );
+    if !options.is_empty() {
+        field(&mut text, "Claude", options);
+    }
+    if schedule.continue_after_limit {
+        field(
+            &mut text,
+            "Usage limit",
+            "continue the session after the limit",
+        );
+    }
+    if let Some(claimed_by) = &schedule.claimed_by {
+        field(
+            &mut text,
+            "Running",
+            format!(
+                "claimed by {claimed_by} since {}",
+                schedule.started_at.as_deref().unwrap_or("-")
+            ),
+        );
+    }
+    if !schedule._memo.is_empty() {
+        field(&mut text, "Memo", &schedule._memo);
     }
 
-    // Setup logging
-    if let Some(_log_path) = log_file {
-        // TODO: Implement file logging
-        eprintln!("File logging not yet implemented. Using stdout/stderr.");
+    text.push_str("\nCommand:\n");
+    text.push_str(&schedule.command);
+    text.push('\n');
+
+    text.push_str("\nRecent runs:\n");
+    if runs.is_empty() {
+        text.push_str("  (none)\n");
+    }
+    for run in runs {
+        text.push_str(&format!(
+            "  {} {}  {}  {}  exit {}\n",
+            status_emoji(&run.status),
+            run.executed_at,
+            run.id,
+            format_run_duration(run.duration_ms),
+            run.exit_code
+                .map_or_else(|| "-".to_string(), |code| code.to_string())
+        ));
     }
 
-    println!("🚀 Starting Claude Scheduler daemon...");
-    println!("  Port: {port}");
-    println!("  Check interval: {interval}s");
+    text
+}
 
-    // Schedule checker loop
-    let mut interval_timer = tokio_interval(Duration::from_secs(interval));
+fn describe_execution(execution: &ExecutionHistory) -> String {
+    let mut text = String::new();
+    field(&mut text, "Execution", &execution.id);
+    field(
+        &mut text,
+        "Status",
+        format!(
+            "{} {} (exit code {})",
+            status_emoji(&execution.status),
+            execution.status,
+            execution
+                .exit_code
+                .map_or_else(|| "-".to_string(), |code| code.to_string())
+        ),
+    );
+    field(&mut text, "Type", execution.execution_type.to_db_string());
+    field(&mut text, "Started", &execution.executed_at);
+    if let Some(finished_at) = &execution.finished_at {
+        field(&mut text, "Finished", finished_at);
+    }
+    field(
+        &mut text,
+        "Duration",
+        format_run_duration(execution.duration_ms),
+    );
+    field(&mut text, "Branch", &execution.branch);
+    field(&mut text, "Path", &execution.execution_path);
+    if let Some(schedule_id) = &execution.schedule_id {
+        let attempt = execution
+            .attempt
+            .map(|attempt| format!(" (attempt {attempt})"))
+            .unwrap_or_default();
+        field(&mut text, "Schedule", format!("{schedule_id}{attempt}"));
+    }
+    if let Some(batch_id) = &execution.batch_id {
+        field(&mut text, "Batch", batch_id);
+    }
+    let options = claude_options(
+        execution.claude_skip_permissions,
+        execution.claude_continue_from_last,
+    );
+    if !options.is_empty() {
+        field(&mut text, "Claude", options);
+    }
+
+    text.push_str("\nCommand:\n");
+    text.push_str(&execution.command);
+    text.push('\n');
+
+    text.push_str("\nOutput:\n");
+    if execution.output.trim().is_empty() {
+        text.push_str("(no output)\n");
+    } else {
+        text.push_str(&execution.output);
+        if !execution.output.ends_with('\n') {
+            text.push('\n');
+        }
+    }
+
+    text
+}
+
+/// Print the output of an execution. With `follow` new lines are printed as
+/// a daemon records them until the execution has finished.
+pub async fn show_logs(
+    db: &Database,
+    id: &str,
+    follow: bool,
+    since: Option<&str>,
+    grep: Option<&str>,
+    stderr: bool,
+) -> Result<()> {
+    let execution_id = find_execution_id(db, id).await?;
+    let mut filter = OutputFilter {
+        after_line: 0,
+        stream: stderr.then_some(OutputStream::Stderr),
+        since: since
+            .map(crate::cli_commands::parse_since)
+            .transpose()?
+            .map(|since| since.format("%Y-%m-%d %H:%M:%S").to_string()),
+        contains: grep.map(str::to_string),
+    };
+
+    // Only runs by a daemon record their lines; others have just the history
+    if !db.has_output_lines(&execution_id).await? {
+        if let Some(execution) = db.get_execution(&execution_id).await? {
+            return write_stdout(&history_output(&execution, &filter));
+        }
+    }
 
     loop {
-        interval_timer.tick().await;
-
-        // Check for pending schedules
-        let schedules = db
-            .get_schedules(Some(ScheduleStatus::Pending), None)
-            .await?;
-
-        let now = Local::now();
-
-        for schedule in schedules {
-            if let Some(scheduled_time_str) = &schedule.scheduled_time {
-                if let Ok(scheduled_time) =
-                    NaiveDateTime::parse_from_str(scheduled_time_str, "%Y-%m-%dT%H:%M")
-                {
-                    let scheduled_local = scheduled_time.and_local_timezone(Local).unwrap();
-
-                    if now >= scheduled_local {
-                        println!("⏰ Executing scheduled command: {}", schedule.command);
-
-                        // Execute the command
-                        let execution_path = if git::is_git_repository(&schedule.execution_path) {
-                            git::get_worktree_path(&schedule.branch)
-                                .unwrap_or(schedule.execution_path.clone())
-                        } else {
-                            schedule.execution_path.clone()
-                        };
-
-                        let (success, output) = crate::cli_commands::execute_command_internal(
-                            &schedule.command,
-                            schedule.is_shell_mode,
-                            &execution_path,
-                            schedule.claude_skip_permissions,
-                            schedule.claude_continue_from_last,
-                        )
-                        .await?;
-
-                        // Update schedule status
-                        let new_status = if success {
-                            ScheduleStatus::Completed
-                        } else {
-                            ScheduleStatus::Failed
-                        };
-
-                        db.update_schedule_status(&schedule.id, new_status).await?;
-
-                        // Create execution history
-                        let history = ExecutionHistory {
-                            id: format!(
-                                "exec_{}",
-                                chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0)
-                            ),
-                            command: schedule.command.clone(),
-                            executed_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
-                            execution_type: ExecutionType::FromSchedule,
-                            status: if success {
-                                ExecutionStatus::Success
-                            } else {
-                                ExecutionStatus::Failed
-                            },
-                            output,
-                            branch: schedule.branch.clone(),
-                            execution_path,
-                            claude_skip_permissions: schedule.claude_skip_permissions,
-                            claude_continue_from_last: schedule.claude_continue_from_last,
-                        };
-
-                        db.create_execution_history(&history).await?;
-
-                        println!(
-                            "  Status: {}",
-                            if success { "✅ Success" } else { "❌ Failed" }
-                        );
-                    }
-                }
+        // Looked up before reading, so that no line written before the run
+        // finished is missed
+        let finished = db.get_execution(&execution_id).await?;
+        let running =
+            finished.is_none() && db.get_execution_process(&execution_id).await?.is_some();
+
+        let lines = db.get_output_lines(&execution_id, &filter).await?;
+        if let Some(last) = lines.last() {
+            filter.after_line = last.line;
+        }
+        let text: String = lines
+            .iter()
+            .map(|line| format!("{}\n", line.text))
+            .collect();
+        if let Err(e) = std::io::stdout().lock().write_all(text.as_bytes()) {
+            if e.kind() == std::io::ErrorKind::BrokenPipe {
+                return Ok(());
             }
+            return Err(e.into());
+        }
+
+        if !follow {
+            return Ok(());
+        }
+        if let Some(execution) = finished {
+            eprintln!(
+                "{} Execution {execution_id} finished: {} (exit code {})",
+                status_emoji(&execution.status),
+                execution.status,
+                execution
+                    .exit_code
+                    .map_or_else(|| "-".to_string(), |code| code.to_string())
+            );
+            return Ok(());
+        }
+        if !running {
+            bail!("Execution {execution_id} stopped without being recorded; its daemon may have been killed");
+        }
+
+        tokio::time::sleep(LOGS_POLL_INTERVAL).await;
+    }
+}
+
+/// Output of an execution without recorded lines, filtered like recorded
+/// lines. Having no times of its own, every line counts as written when the
+/// execution finished.
+fn history_output(execution: &ExecutionHistory, filter: &OutputFilter) -> String {
+    let finished_at = execution
+        .finished_at
+        .as_deref()
+        .unwrap_or(&execution.executed_at);
+    if filter
+        .since
+        .as_deref()
+        .is_some_and(|since| finished_at < since)
+    {
+        return String::new();
+    }
+
+    let output = match filter.stream {
+        Some(OutputStream::Stderr) => &execution.stderr,
+        _ => &execution.output,
+    };
+    output
+        .lines()
+        .filter(|line| {
+            filter
+                .contains
+                .as_deref()
+                .is_none_or(|text| line.contains(text))
+        })
+        .map(|line| format!("{line}\n"))
+        .collect()
+}
+
+/// Append a "Label:  value" line with the values aligned
+fn field(text: &mut String, label: &str, value: impl std::fmt::Display) {
+    text.push_str(&format!("{:<13}{value}\n", format!("{label}:")));
+}
+
+fn claude_options(skip_permissions: bool, continue_from_last: bool) -> String {
+    let mut options = Vec::new();
+    if skip_permissions {
+        options.push("--dangerously-skip-permissions");
+    }
+    if continue_from_last {
+        options.push("-c");
+    }
+    options.join(" ")
+}
+
+/// Show text through `$PAGER` (`less` by default) when stdout is a terminal.
+/// Without a pager that can be started the text is printed directly.
+fn page(text: &str) -> Result<()> {
+    if !std::io::stdout().is_terminal() {
+        return write_stdout(text);
+    }
+
+    let pager = std::env::var("PAGER")
+        .ok()
+        .filter(|pager| !pager.trim().is_empty())
+        .unwrap_or_else(|| "less".to_string());
+    let mut words = pager.split_whitespace();
+    let mut command = std::process::Command::new(words.next().unwrap_or("less"));
+    command.args(words).stdin(std::process::Stdio::piped());
+    // Let less quit by itself when the text fits on one screen
+    if std::env::var_os("LESS").is_none() {
+        command.env("LESS", "FRX");
+    }
+
+    let Ok(mut child) = command.spawn() else {
+        return write_stdout(text);
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        // Quitting the pager early closes its input
+        if let Err(e) = stdin.write_all(text.as_bytes()) {
+            if e.kind() != std::io::ErrorKind::BrokenPipe {
+                return Err(e.into());
+            }
+        }
+    }
+    child.wait()?;
+
+    Ok(())
+}
+
+/// Print to stdout, stopping quietly when the reader has gone away
+/// (e.g. `| head`)
+fn write_stdout(text: &str) -> Result<()> {
+    match std::io::stdout().lock().write_all(text.as_bytes()) {
+        Err(e) if e.kind() == std::io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+/// Print the schedules that depend on each other as a tree, or as a Graphviz digraph
+pub async fn show_graph(db: &Database, format: &str) -> Result<()> {
+    let mut schedules = db.get_schedules(None, None).await?;
+    schedules.reverse();
+
+    let linked: HashSet<&str> = schedules
+        .iter()
+        .filter(|schedule| !schedule.depends_on.is_empty())
+        .flat_map(|schedule| {
+            std::iter::once(schedule.id.as_str())
+                .chain(schedule.depends_on.iter().map(String::as_str))
+        })
+        .collect();
+    let schedules: Vec<&Schedule> = schedules
+        .iter()
+        .filter(|schedule| linked.contains(schedule.id.as_str()))
+        .collect();
+
+    if format == "dot" {
+        println!("digraph schedules {{");
+        for schedule in &schedules {
+            println!(
+                "  {:?} [label={:?}];",
+                schedule.id,
+                format!("{}\n{}", schedule.command, schedule.status.to_db_string())
+            );
+        }
+        for schedule in &schedules {
+            for parent in &schedule.depends_on {
+                println!("  {parent:?} -> {:?};", schedule.id);
+            }
+        }
+        println!("}}");
+        return Ok(());
+    }
+
+    if schedules.is_empty() {
+        println!("No schedules depend on other schedules");
+        return Ok(());
+    }
+
+    let mut children: HashMap<&str, Vec<&Schedule>> = HashMap::new();
+    for schedule in &schedules {
+        for parent in &schedule.depends_on {
+            children.entry(parent.as_str()).or_default().push(schedule);
         }
     }
+
+    // Roots are schedules whose dependencies are all gone (or that have none)
+    let by_id: HashSet<&str> = schedules
+        .iter()
+        .map(|schedule| schedule.id.as_str())
+        .collect();
+    for root in schedules.iter().filter(|schedule| {
+        schedule
+            .depends_on
+            .iter()
+            .all(|parent| !by_id.contains(parent.as_str()))
+    }) {
+        println!("{}", graph_node(root));
+        print_graph_children(root, &children, "");
+    }
+
+    Ok(())
+}
+
+fn print_graph_children(
+    schedule: &Schedule,
+    children: &HashMap<&str, Vec<&Schedule>>,
+    prefix: &str,
+) {
+    let Some(nodes) = children.get(schedule.id.as_str()) else {
+        return;
+    };
+    for (index, child) in nodes.iter().enumerate() {
+        let last = index + 1 == nodes.len();
+        println!(
+            "{prefix}{}{}",
+            if last { "└── " } else { "├── " },
+            graph_node(child)
+        );
+        let prefix = format!("{prefix}{}", if last { "    " } else { "│   " });
+        print_graph_children(child, children, &prefix);
+    }
+}
+
+fn graph_node(schedule: &Schedule) -> String {
+    let mut node = format!(
+        "{} [{}] {}",
+        schedule.id,
+        schedule.status.to_db_string(),
+        schedule.command
+    );
+    if schedule.on_dependency_failure != DependencyFailurePolicy::Skip
+        && !schedule.depends_on.is_empty()
+    {
+        node.push_str(&format!(
+            " (on failure: {})",
+            schedule.on_dependency_failure.to_db_string()
+        ));
+    }
+    node
+}
+
+/// Apply pending migrations, or with `status` only show which have been applied
+pub async fn migrate_database(path: &std::path::Path, status: bool) -> Result<()> {
+    let db = Database::connect(path).await?;
+
+    if !status {
+        let applied = db.migrate().await?;
+        for migration in &applied {
+            println!(
+                "✅ Applied migration {}: {}",
+                migration.version, migration.description
+            );
+        }
+        if applied.is_empty() {
+            println!(
+                "✅ The database is up to date (schema version {})",
+                crate::migrations::latest_version()
+            );
+        }
+        return Ok(());
+    }
+
+    let states = db.migration_status().await?;
+    let mut table = Table::new();
+    table.set_content_arrangement(ContentArrangement::Dynamic);
+    table.set_header(vec!["Version", "Status", "Applied At", "Description"]);
+    for state in &states {
+        let status = match (&state.applied_at, state.unknown) {
+            (_, true) => "unknown",
+            (Some(_), false) => "applied",
+            (None, false) => "pending",
+        };
+        table.add_row(vec![
+            state.version.to_string(),
+            status.to_string(),
+            state.applied_at.clone().unwrap_or_default(),
+            state.description.clone(),
+        ]);
+    }
+
+    println!("Database: {}", path.display());
+    println!("{table}");
+
+    let pending = states
+        .iter()
+        .filter(|state| state.applied_at.is_none())
+        .count();
+    if states.iter().any(|state| state.unknown) {
+        println!(
+            "⚠️  The database was migrated by a newer claude-scheduler (this one supports up to schema version {})",
+            crate::migrations::latest_version()
+        );
+    } else if pending > 0 {
+        println!("{pending} pending migration(s), applied on the next start or with `claude-scheduler db migrate`");
+    }
+
+    Ok(())
+}
+
+pub async fn run_daemon(
+    db: &Database,
+    port: u16,
+    interval: Option<u64>,
+    config: &Config,
+) -> Result<()> {
+    use crate::daemon::{DaemonSettings, DaemonState};
+    use tokio::time::{interval as tokio_interval, MissedTickBehavior};
+
+    let settings = DaemonSettings::from_config(config, interval);
+    info!(
+        "daemon started: pid={} interval={}s grace_period={}s max_concurrency={} version={}",
+        std::process::id(),
+        settings.check_interval.as_secs(),
+        settings.grace_period.as_secs(),
+        settings.max_concurrency,
+        env!("CARGO_PKG_VERSION")
+    );
+
+    crate::daemon::recover_orphaned_schedules(db, config.daemon.orphan_policy).await?;
+
+    // Serve the HTTP API next to the schedule checker. Binding up front makes
+    // an occupied port a startup error instead of a silent background failure.
+    let state = DaemonState::new(db.clone(), settings);
+    crate::daemon::recover_orphaned_executions(db, &state.daemon_id).await?;
+    let listener = crate::api::bind(port).await?;
+    info!("api listening: url=http://{}", listener.local_addr()?);
+    let api_state = state.clone();
+    tokio::spawn(async move {
+        if let Err(e) = crate::api::serve(listener, api_state).await {
+            error!("api server stopped: error={e}");
+        }
+    });
+
+    tokio::spawn(crate::daemon::handle_signals(state.clone(), interval));
+    tokio::spawn(crate::daemon::watch_cancel_requests(state.clone()));
+
+    // Schedule checker loop. A failing tick is logged and retried on the
+    // next one instead of stopping the daemon.
+    let mut check_interval = state.settings().check_interval;
+    let mut interval_timer = tokio_interval(check_interval);
+    interval_timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = state.shutdown.cancelled() => break,
+            _ = interval_timer.tick() => {}
+        }
+
+        if let Err(e) = crate::daemon::run_due_schedules(&state).await {
+            error!("schedule check failed: error={e:#}");
+        }
+
+        // Pick up an interval changed by a configuration reload
+        let reloaded_interval = state.settings().check_interval;
+        if reloaded_interval != check_interval {
+            check_interval = reloaded_interval;
+            interval_timer = tokio_interval(check_interval);
+            interval_timer.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            interval_timer.tick().await;
+        }
+    }
+
+    // Wait for queued and running executions
+    state.tasks.close();
+    state.tasks.wait().await;
+
+    info!("daemon stopped");
+    Ok(())
 }
@@ -3,110 +3,103 @@ use chrono::Local;
 #[cfg(feature = "gui")]
 use dioxus::prelude::*;
 
+#[cfg(feature = "gui")]
+use crate::database::Database;
 #[cfg(feature = "gui")]
 use crate::git::*;
 #[cfg(feature = "gui")]
 use crate::models::*;
 #[cfg(feature = "gui")]
-use crate::persistence;
-#[cfg(feature = "gui")]
 use crate::utils::*;
 
-/// スケジュールを5秒ごとにチェックする関数
-fn schedule_checker(
+/// CLI・デーモンと共有するデータベースを開く（以前のGUIのJSONデータも取り込む）
+async fn open_database() -> anyhow::Result<Database> {
+    let config = crate::config::Config::load()?;
+    let db = Database::new(&std::path::absolute(config.database_path())?).await?;
+
+    let summary = crate::persistence::import_legacy_data(&db).await?;
+    if summary != crate::persistence::ImportSummary::default() {
+        println!(
+            "📦 JSONファイルからスケジュール{}件・実行履歴{}件を取り込みました",
+            summary.schedules, summary.history
+        );
+    }
+    Ok(db)
+}
+
+/// データベースからスケジュールと実行履歴を読み込み直す（古い順に表示するため逆順にする）
+async fn reload_data(
+    db: &Database,
     mut schedules: Signal<Vec<Schedule>>,
     mut execution_history: Signal<Vec<ExecutionHistory>>,
 ) {
-    use_effect(move || {
-        spawn(async move {
-            loop {
-                tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
+    if let Ok(mut loaded) = db.get_schedules(None, None).await {
+        loaded.reverse();
+        schedules.set(loaded);
+    }
+    if let Ok(mut loaded) = db
+        .get_execution_history(&crate::database::HistoryFilter::default())
+        .await
+    {
+        loaded.reverse();
+        execution_history.set(loaded);
+    }
+}
 
-                let mut schedules_to_execute = Vec::new();
-                schedules.with(|s| {
-                    for schedule in s.iter() {
-                        if schedule.status == ScheduleStatus::Pending {
-                            if let Some(scheduled_time) = &schedule.scheduled_time {
-                                if is_time_reached(scheduled_time) {
-                                    schedules_to_execute.push(schedule.clone());
-                                }
-                            }
-                        }
-                    }
-                });
+/// 検索結果のスニペットで一致部分を囲む記号（実行結果には含まれない制御文字）
+const MATCH_START: &str = "\u{2}";
+const MATCH_END: &str = "\u{3}";
+
+/// スニペットを（テキスト, 一致部分かどうか）の並びに分ける
+fn snippet_fragments(snippet: &str) -> Vec<(String, bool)> {
+    let mut fragments = Vec::new();
+    for (index, part) in snippet.split(MATCH_START).enumerate() {
+        match part.split_once(MATCH_END) {
+            Some((matched, rest)) if index > 0 => {
+                fragments.push((matched.to_string(), true));
+                fragments.push((rest.to_string(), false));
+            }
+            _ => fragments.push((part.to_string(), false)),
+        }
+    }
+    fragments
+}
 
-                for schedule in schedules_to_execute {
-                    let command = if schedule.is_shell_mode {
-                        schedule.command.clone()
-                    } else {
-                        let mut claude_cmd = String::from("claude");
-                        if schedule.claude_skip_permissions {
-                            claude_cmd.push_str(" --dangerously-skip-permissions");
-                        }
-                        if schedule.claude_continue_from_last {
-                            claude_cmd.push_str(" -c");
-                        }
-                        format!("{} -p \"{}\"", claude_cmd, schedule.command)
-                    };
-
-                    let result =
-                        if schedule.branch != "main" && schedule.branch != get_current_branch() {
-                            execute_command_in_worktree(
-                                &command,
-                                &schedule.branch,
-                                schedule.is_shell_mode,
-                                &schedule.execution_path,
-                                schedule.claude_skip_permissions,
-                                schedule.claude_continue_from_last,
-                            )
-                        } else {
-                            execute_command_in_directory(&command, &schedule.execution_path)
-                        };
+/// データベースを開き、スケジュールを5秒ごとにチェックする関数
+///
+/// 実行時刻になったスケジュールはデーモンと同じ処理で実行する。
+/// 実行前に実行時刻を条件にスケジュールを確保するため、デーモンを同時に
+/// 起動していても先に確保した側だけが実行する。
+fn schedule_checker(
+    mut database: Signal<Option<Database>>,
+    schedules: Signal<Vec<Schedule>>,
+    execution_history: Signal<Vec<ExecutionHistory>>,
+) {
+    use_effect(move || {
+        spawn(async move {
+            let db = match open_database().await {
+                Ok(db) => db,
+                Err(e) => {
+                    eprintln!("❌ データベースを開けませんでした: {e:#}");
+                    return;
+                }
+            };
+            database.set(Some(db.clone()));
 
-                    let (status, output) = match result {
-                        Ok(output) => {
-                            let stdout = String::from_utf8_lossy(&output.stdout);
-                            let stderr = String::from_utf8_lossy(&output.stderr);
+            let config = crate::config::Config::load().unwrap_or_default();
+            let state = crate::daemon::DaemonState::new(
+                db.clone(),
+                crate::daemon::DaemonSettings::from_config(&config, None),
+            );
+            // `kill` コマンドからGUIが実行中のスケジュールも停止できるようにする
+            tokio::spawn(crate::daemon::watch_cancel_requests(state.clone()));
 
-                            if output.status.success() {
-                                (ScheduleStatus::Completed, format!("{stdout}\n{stderr}"))
-                            } else {
-                                (ScheduleStatus::Failed, format!("{stdout}\n{stderr}"))
-                            }
-                        }
-                        Err(e) => (ScheduleStatus::Failed, format!("エラー: {e}")),
-                    };
+            loop {
+                reload_data(&db, schedules, execution_history).await;
+                tokio::time::sleep(tokio::time::Duration::from_secs(5)).await;
 
-                    // スケジュールのステータスを更新
-                    schedules.with_mut(|s| {
-                        if let Some(sch) = s.iter_mut().find(|sch| sch.id == schedule.id) {
-                            sch.status = status.clone();
-                        }
-                    });
-
-                    // 実行履歴に追加
-                    let history = ExecutionHistory {
-                        id: format!("history_{}", chrono::Utc::now().timestamp_micros()),
-                        command: schedule.command.clone(),
-                        executed_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
-                        execution_type: ExecutionType::FromSchedule,
-                        status: if status == ScheduleStatus::Completed {
-                            ExecutionStatus::Success
-                        } else {
-                            ExecutionStatus::Failed
-                        },
-                        output,
-                        branch: schedule.branch.clone(),
-                        execution_path: schedule.execution_path.clone(),
-                        claude_skip_permissions: schedule.claude_skip_permissions,
-                        claude_continue_from_last: schedule.claude_continue_from_last,
-                    };
-
-                    execution_history.with_mut(|h| {
-                        h.push(history);
-                        // 実行履歴を永続化
-                        let _ = persistence::save_execution_history(h);
-                    });
+                if let Err(e) = crate::daemon::run_due_schedules(&state).await {
+                    eprintln!("❌ スケジュールの実行に失敗しました: {e:#}");
                 }
             }
         });
@@ -163,17 +156,24 @@ pub fn app() -> Element {
         false // デフォルトはライトモード
     });
     let mut is_executing = use_signal(|| false);
+    let mut running_execution = use_signal(|| None::<tokio_util::sync::CancellationToken>);
 
-    // 永続化されたデータを読み込み
-    let mut schedules = use_signal(|| persistence::load_schedules().unwrap_or_default());
+    // データベース（開くまではNone）とその内容
+    let database = use_signal(|| None::<Database>);
+    let schedules = use_signal(Vec::<Schedule>::new);
     let mut use_schedule = use_signal(|| false);
     let mut is_tomorrow = use_signal(|| false);
     let mut selected_hour = use_signal(|| 9u32);
     let mut selected_minute = use_signal(|| 0u32);
+    let mut cron_text = use_signal(String::new);
 
-    // 実行履歴用の状態（永続化データを読み込み）
-    let mut execution_history =
-        use_signal(|| persistence::load_execution_history().unwrap_or_default());
+    // 実行履歴用の状態
+    let execution_history = use_signal(Vec::<ExecutionHistory>::new);
+    // 実行履歴の検索語と検索結果（検索中はNone、検索できなかった場合はエラーメッセージ）
+    let mut history_search = use_signal(String::new);
+    let mut search_hits = use_signal(|| None::<Result<Vec<SearchHit>, String>>);
+    // 共有の実行履歴をすべて削除する前の確認表示
+    let mut confirm_clear_history = use_signal(|| false);
 
     // シェルモード実行用の状態
     let mut use_shell_mode = use_signal(|| false);
@@ -208,7 +208,7 @@ pub fn app() -> Element {
     let mut last_execution_path = use_signal(String::new);
 
     // 定期的なスケジュールチェック（5秒ごと）
-    schedule_checker(schedules, execution_history);
+    schedule_checker(database, schedules, execution_history);
 
     // Claude Code実行関数
     let execute_command = move |_: Event<MouseData>| {
@@ -223,124 +223,77 @@ pub fn app() -> Element {
         let branch = selected_branch();
         let use_worktree = use_git_worktree();
         let exec_path = execution_path();
+        // 停止ボタンから実行中のコマンドを止めるためのトークン
+        let cancel = tokio_util::sync::CancellationToken::new();
+        running_execution.set(Some(cancel.clone()));
         spawn(async move {
-            let result = if use_worktree && branch != "main" && branch != get_current_branch() {
-                // Git Worktreeを使用
-                execute_command_in_worktree(
-                    &prompt,
-                    &branch,
-                    shell_mode,
-                    &exec_path,
-                    claude_skip_permissions(),
-                    claude_continue_from_last(),
-                )
+            let executed_at = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+            let use_worktree = use_worktree && branch != "main" && branch != get_current_branch();
+            let run_path = if use_worktree {
+                // Git Worktreeを使用（なければ作成）
+                create_worktree(&exec_path, &branch)
             } else {
-                // 通常の実行
-                let command = if shell_mode {
-                    prompt.clone()
-                } else {
-                    let mut claude_cmd = String::from("claude");
-                    if claude_skip_permissions() {
-                        claude_cmd.push_str(" --dangerously-skip-permissions");
-                    }
-                    if claude_continue_from_last() {
-                        claude_cmd.push_str(" -c");
-                    }
-                    format!("{claude_cmd} -p \"{prompt}\"")
-                };
-                execute_command_in_directory(&command, &exec_path)
+                Ok(expand_path(&exec_path))
             };
 
-            let _ = match result {
-                Ok(output) => {
-                    let stdout = String::from_utf8_lossy(&output.stdout);
-                    let stderr = String::from_utf8_lossy(&output.stderr);
-
-                    let (result_msg, exec_status) = if output.status.success() {
-                        (
-                            format!(
-                                "✅ 実行成功 [{}]:\n{}",
-                                Local::now().format("%H:%M:%S"),
-                                stdout
-                            ),
-                            ExecutionStatus::Success,
-                        )
-                    } else {
-                        (
-                            format!(
-                                "❌ 実行失敗 [{}]:\n{}\n{}",
-                                Local::now().format("%H:%M:%S"),
-                                stdout,
-                                stderr
-                            ),
-                            ExecutionStatus::Failed,
-                        )
-                    };
-
-                    // 履歴に記録
-                    let history = ExecutionHistory {
-                        id: format!("history_{}", chrono::Utc::now().timestamp_micros()),
-                        command: prompt.clone(),
-                        executed_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
-                        execution_type: if shell_mode {
-                            ExecutionType::ShellMode
-                        } else {
-                            ExecutionType::Manual
-                        },
-                        status: exec_status,
-                        output: format!("{stdout}\n{stderr}"),
-                        branch: if use_worktree {
-                            branch.clone()
-                        } else {
-                            get_current_branch()
-                        },
-                        execution_path: exec_path.clone(),
-                        claude_skip_permissions: claude_skip_permissions(),
-                        claude_continue_from_last: claude_continue_from_last(),
-                    };
-                    execution_history.with_mut(|h| {
-                        h.push(history);
-                        // 実行履歴を永続化
-                        let _ = persistence::save_execution_history(h);
-                    });
-
-                    result_msg
-                }
-                Err(e) => {
-                    let error_msg =
-                        format!("❌ エラー [{}]: {}", Local::now().format("%H:%M:%S"), e);
-
-                    // 履歴に記録
-                    let history = ExecutionHistory {
-                        id: format!("history_{}", chrono::Utc::now().timestamp_micros()),
-                        command: prompt.clone(),
-                        executed_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
-                        execution_type: if shell_mode {
-                            ExecutionType::ShellMode
-                        } else {
-                            ExecutionType::Manual
-                        },
-                        status: ExecutionStatus::Failed,
-                        output: format!("エラー: {e}"),
-                        branch: if use_worktree {
-                            branch.clone()
-                        } else {
-                            get_current_branch()
+            let result = match run_path {
+                Ok(run_path) => {
+                    crate::cli_commands::execute_command_internal(
+                        &prompt,
+                        shell_mode,
+                        &run_path,
+                        claude_skip_permissions(),
+                        claude_continue_from_last(),
+                        crate::cli_commands::ExecutionControl {
+                            cancel: Some(&cancel),
+                            ..Default::default()
                         },
-                        execution_path: exec_path,
-                        claude_skip_permissions: claude_skip_permissions(),
-                        claude_continue_from_last: claude_continue_from_last(),
-                    };
-                    execution_history.with_mut(|h| {
-                        h.push(history);
-                        // 実行履歴を永続化
-                        let _ = persistence::save_execution_history(h);
-                    });
-
-                    error_msg
+                    )
+                    .await
                 }
+                Err(e) => Err(e),
             };
 
+            // 停止した場合も途中までの出力を履歴に残す
+            let outcome =
+                result.unwrap_or_else(|e| crate::cli_commands::CommandOutcome::failed(&e));
+
+            let history = ExecutionHistory {
+                id: crate::daemon::new_execution_id(),
+                command: prompt.clone(),
+                executed_at,
+                execution_type: if shell_mode {
+                    ExecutionType::ShellMode
+                } else {
+                    ExecutionType::Manual
+                },
+                status: outcome.status,
+                output: outcome.output,
+                branch: if use_worktree {
+                    branch.clone()
+                } else {
+                    get_current_branch()
+                },
+                execution_path: exec_path,
+                claude_skip_permissions: claude_skip_permissions(),
+                claude_continue_from_last: claude_continue_from_last(),
+                schedule_id: None,
+                attempt: None,
+                exit_code: outcome.exit_code,
+                batch_id: None,
+                stdout: outcome.stdout,
+                stderr: outcome.stderr,
+                finished_at: Some(Local::now().format("%Y-%m-%d %H:%M:%S").to_string()),
+                duration_ms: Some(outcome.duration.as_millis() as u64),
+            };
+            if let Some(db) = database() {
+                if let Err(e) = db.create_execution_history(&history).await {
+                    eprintln!("❌ 実行履歴を保存できませんでした: {e:#}");
+                }
+                reload_data(&db, schedules, execution_history).await;
+            }
+
+            running_execution.set(None);
             is_executing.set(false);
         });
     };
@@ -349,35 +302,64 @@ pub fn app() -> Element {
     let add_schedule = move |_: Event<MouseData>| {
         let prompt = text_content().clone();
         if !prompt.trim().is_empty() {
+            // cron式が入力されている場合は繰り返しスケジュールとして登録
+            let cron_expression = if use_schedule() && !cron_text().trim().is_empty() {
+                if cron_text().parse::<crate::cron::CronExpression>().is_err() {
+                    return;
+                }
+                Some(cron_text().trim().to_string())
+            } else {
+                None
+            };
+
             let scheduled_time = if use_schedule() {
-                Some(build_scheduled_time(
-                    is_tomorrow(),
-                    selected_hour(),
-                    selected_minute(),
-                ))
+                let recurring = Schedule {
+                    cron_expression: cron_expression.clone(),
+                    ..Default::default()
+                };
+                recurring
+                    .next_occurrence(Local::now().naive_local())
+                    .or_else(|| {
+                        Some(build_scheduled_time(
+                            is_tomorrow(),
+                            selected_hour(),
+                            selected_minute(),
+                        ))
+                    })
             } else {
                 None
             };
 
-            if let Some(edit_sched) = editing_schedule() {
+            let Some(db) = database() else {
+                return;
+            };
+
+            if let Some(mut sched) = editing_schedule() {
                 // 編集モード: 既存のスケジュールを更新
-                schedules.with_mut(|s| {
-                    if let Some(sched) = s.iter_mut().find(|s| s.id == edit_sched.id) {
-                        sched.command = prompt;
-                        sched.scheduled_time = scheduled_time;
-                        sched._memo = memo_text();
-                        sched.is_shell_mode = use_shell_mode();
-                        sched.branch = if use_git_worktree() {
-                            selected_branch()
-                        } else {
-                            get_current_branch()
-                        };
-                        sched.execution_path = execution_path();
-                        sched.claude_skip_permissions = claude_skip_permissions();
-                        sched.claude_continue_from_last = claude_continue_from_last();
+                sched.command = prompt;
+                sched.scheduled_time = scheduled_time;
+                sched._memo = memo_text();
+                sched.is_shell_mode = use_shell_mode();
+                sched.branch = if use_git_worktree() {
+                    selected_branch()
+                } else {
+                    get_current_branch()
+                };
+                sched.execution_path = execution_path();
+                sched.claude_skip_permissions = claude_skip_permissions();
+                sched.claude_continue_from_last = claude_continue_from_last();
+                sched.cron_expression = cron_expression;
+
+                spawn(async move {
+                    // 実行中のスケジュールは変更されない
+                    match db.update_schedule(&sched).await {
+                        Ok(true) => {}
+                        Ok(false) => {
+                            eprintln!("⚠️ 実行中のスケジュールは編集できません: {}", sched.id)
+                        }
+                        Err(e) => eprintln!("❌ スケジュールを保存できませんでした: {e:#}"),
                     }
-                    // スケジュールを永続化
-                    let _ = persistence::save_schedules(s);
+                    reload_data(&db, schedules, execution_history).await;
                 });
 
                 // 編集モードを解除
@@ -385,7 +367,10 @@ pub fn app() -> Element {
             } else {
                 // 新規作成モード
                 let schedule = Schedule {
-                    id: format!("schedule_{}", chrono::Utc::now().timestamp()),
+                    id: format!(
+                        "schedule_{}",
+                        chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0)
+                    ),
                     command: prompt,
                     scheduled_time,
                     _memo: memo_text(),
@@ -400,12 +385,15 @@ pub fn app() -> Element {
                     execution_path: execution_path(),
                     claude_skip_permissions: claude_skip_permissions(),
                     claude_continue_from_last: claude_continue_from_last(),
+                    cron_expression,
+                    ..Default::default()
                 };
 
-                schedules.with_mut(|s| {
-                    s.push(schedule);
-                    // スケジュールを永続化
-                    let _ = persistence::save_schedules(s);
+                spawn(async move {
+                    if let Err(e) = db.create_schedule(&schedule).await {
+                        eprintln!("❌ スケジュールを保存できませんでした: {e:#}");
+                    }
+                    reload_data(&db, schedules, execution_history).await;
                 });
             }
 
@@ -416,6 +404,7 @@ pub fn app() -> Element {
             is_tomorrow.set(false);
             selected_hour.set(9);
             selected_minute.set(0);
+            cron_text.set(String::new());
         }
     };
 
@@ -950,6 +939,41 @@ pub fn app() -> Element {
                                     {format!("{:02}:{:02}", selected_hour(), selected_minute())}
                                 }
                             }
+
+                            div {
+                                style: "margin-bottom: 15px;",
+                                h4 {
+                                    style: "margin: 0 0 8px 0; color: {text_color}; font-size: 0.9rem;",
+                                    "🔁 繰り返し (cron式)"
+                                }
+
+                                input {
+                                    r#type: "text",
+                                    value: cron_text(),
+                                    oninput: move |evt| cron_text.set(evt.value()),
+                                    placeholder: "例: 0 3 * * 1-5, @daily（空欄の場合は1回のみ実行）",
+                                    style: "width: 100%; padding: 6px 8px; border: 1px solid {border_color}; border-radius: 4px; background: {textarea_bg}; color: {text_color}; font-family: monospace; font-size: 0.85rem;",
+                                }
+
+                                if !cron_text().trim().is_empty() {
+                                    {match cron_text().parse::<crate::cron::CronExpression>() {
+                                        Ok(expression) => rsx! {
+                                            p {
+                                                style: "margin: 6px 0 0 0; font-size: 0.75rem; color: #16a34a;",
+                                                "✅ 次回実行: "
+                                                {expression.next_after(Local::now().naive_local()).map(|next| next.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_default()}
+                                                "（実行日・時刻の指定より優先されます）"
+                                            }
+                                        },
+                                        Err(e) => rsx! {
+                                            p {
+                                                style: "margin: 6px 0 0 0; font-size: 0.75rem; color: #dc2626;",
+                                                "❌ 無効なcron式: " {e.to_string()}
+                                            }
+                                        },
+                                    }}
+                                }
+                            }
                         }
                     }
                 }
@@ -972,6 +996,19 @@ pub fn app() -> Element {
                             {if is_executing() { "実行中..." } else { "▶️ 即座実行" }}
                         }
 
+                        if is_executing() {
+                            button {
+                                onclick: move |_| {
+                                    if let Some(cancel) = running_execution() {
+                                        cancel.cancel();
+                                    }
+                                },
+                                disabled: running_execution().is_none_or(|cancel| cancel.is_cancelled()),
+                                style: "padding: 8px 16px; background: #dc2626; color: white; border: none; border-radius: 4px; cursor: pointer; font-weight: 500; transition: all 0.2s ease;",
+                                {if running_execution().is_some_and(|cancel| cancel.is_cancelled()) { "停止中..." } else { "⏹️ 停止" }}
+                            }
+                        }
+
                         button {
                             onclick: add_schedule,
                             disabled: text_content().trim().is_empty(),
@@ -1004,6 +1041,7 @@ pub fn app() -> Element {
                                         is_tomorrow.set(false);
                                         selected_hour.set(9);
                                         selected_minute.set(0);
+                                        cron_text.set(String::new());
                                     },
                                     style: "padding: 8px 16px; background: #6b7280; color: white; border: none; border-radius: 4px; cursor: pointer; font-weight: 500; transition: all 0.2s ease;",
                                     "❌ キャンセル"
@@ -1068,6 +1106,12 @@ pub fn app() -> Element {
                                 div {
                                     style: "font-size: 0.8rem; color: #3b82f6; margin: 5px 0; font-weight: 500;",
                                     "⏰ 実行予定: " {scheduled_time.clone()}
+                                    if schedule.is_recurring() {
+                                        " | 🔁 " {schedule.repeat_description()}
+                                    }
+                                    if let Some(remaining) = schedule.remaining_runs() {
+                                        " | 残り" {remaining.to_string()} "回"
+                                    }
                                 }
                             } else {
                                 div {
@@ -1094,6 +1138,7 @@ pub fn app() -> Element {
                                             use_git_worktree.set(!edit_schedule.branch.is_empty() && edit_schedule.branch != "main");
                                             claude_skip_permissions.set(edit_schedule.claude_skip_permissions);
                                             claude_continue_from_last.set(edit_schedule.claude_continue_from_last);
+                                            cron_text.set(edit_schedule.cron_expression.clone().unwrap_or_default());
 
                                             // 時刻を設定
                                             if let Some(time) = &edit_schedule.scheduled_time {
@@ -1120,10 +1165,20 @@ pub fn app() -> Element {
                                     onclick: {
                                         let schedule_id = schedule.id.clone();
                                         move |_: Event<MouseData>| {
-                                            schedules.with_mut(|s| {
-                                                s.retain(|sched| sched.id != schedule_id);
-                                                // スケジュールを永続化
-                                                let _ = persistence::save_schedules(s);
+                                            let Some(db) = database() else {
+                                                return;
+                                            };
+                                            let schedule_id = schedule_id.clone();
+                                            spawn(async move {
+                                                // CLIと同じく実行中のスケジュールは削除しない
+                                                let result = match crate::cli_commands::load_idle_schedule(&db, &schedule_id).await {
+                                                    Ok(_) => db.delete_schedule(&schedule_id).await.map(|_| ()),
+                                                    Err(e) => Err(e),
+                                                };
+                                                if let Err(e) = result {
+                                                    eprintln!("❌ スケジュールを削除できませんでした: {e:#}");
+                                                }
+                                                reload_data(&db, schedules, execution_history).await;
                                             });
                                         }
                                     },
@@ -1147,16 +1202,138 @@ pub fn app() -> Element {
                         "📊 実行履歴・結果 (" {execution_history().len().to_string()} "件)"
                     }
 
-                    if !execution_history().is_empty() {
+                    if confirm_clear_history() {
+                        // 履歴はCLIやデーモンと共有しているため、削除する前に確認する
+                        div {
+                            style: "display: flex; align-items: center; gap: 8px;",
+                            span {
+                                style: "color: {text_color}; font-size: 0.85rem;",
+                                "CLIやデーモンの分も含め、すべての実行履歴を削除しますか？"
+                            }
+                            button {
+                                onclick: move |_: Event<MouseData>| {
+                                    confirm_clear_history.set(false);
+                                    let Some(db) = database() else {
+                                        return;
+                                    };
+                                    spawn(async move {
+                                        if let Err(e) = db.clear_execution_history().await {
+                                            eprintln!("❌ 実行履歴を削除できませんでした: {e:#}");
+                                        }
+                                        reload_data(&db, schedules, execution_history).await;
+                                    });
+                                },
+                                style: "padding: 6px 12px; background: #dc2626; color: white; border: none; border-radius: 4px; cursor: pointer; font-size: 0.85rem; font-weight: 500;",
+                                "削除する"
+                            }
+                            button {
+                                onclick: move |_: Event<MouseData>| confirm_clear_history.set(false),
+                                style: "padding: 6px 12px; background: #6b7280; color: white; border: none; border-radius: 4px; cursor: pointer; font-size: 0.85rem; font-weight: 500;",
+                                "キャンセル"
+                            }
+                        }
+                    } else if !execution_history().is_empty() {
                         button {
-                            onclick: move |_: Event<MouseData>| execution_history.set(Vec::new()),
+                            onclick: move |_: Event<MouseData>| confirm_clear_history.set(true),
                             style: "padding: 6px 12px; background: #dc2626; color: white; border: none; border-radius: 4px; cursor: pointer; font-size: 0.85rem; font-weight: 500;",
                             "🗑️ 履歴をクリア"
                         }
                     }
                 }
 
-                if execution_history().is_empty() && !is_executing() {
+                input {
+                    r#type: "search",
+                    value: history_search(),
+                    oninput: move |evt| {
+                        let query = evt.value();
+                        history_search.set(query.clone());
+                        if query.trim().is_empty() {
+                            return;
+                        }
+                        if query.split_whitespace().any(|term| term.chars().count() < 3) {
+                            search_hits.set(Some(Err("3文字以上の語を入力してください".to_string())));
+                            return;
+                        }
+                        let Some(db) = database() else {
+                            return;
+                        };
+                        search_hits.set(None);
+                        spawn(async move {
+                            let hits = db
+                                .search_execution_history(&query, 50, (MATCH_START, MATCH_END))
+                                .await
+                                .map_err(|e| {
+                                    eprintln!("❌ 実行履歴を検索できませんでした: {e:#}");
+                                    "検索できませんでした".to_string()
+                                });
+                            // 入力中に古い検索の結果で上書きしない
+                            if history_search() == query {
+                                search_hits.set(Some(hits));
+                            }
+                        });
+                    },
+                    placeholder: "🔍 コマンド・実行結果を検索（3文字以上、空白区切りで複数語）",
+                    style: "width: 100%; padding: 6px 8px; margin-bottom: 15px; border: 1px solid {border_color}; border-radius: 4px; background: {textarea_bg}; color: {text_color}; font-size: 0.85rem; box-sizing: border-box;",
+                }
+
+                if !history_search().trim().is_empty() {
+                    div {
+                        style: "max-height: 400px; overflow-y: auto;",
+                        {match search_hits() {
+                            None => rsx! {
+                                div {
+                                    style: "text-align: center; padding: 20px; opacity: 0.6; color: {text_color};",
+                                    "🔍 検索中..."
+                                }
+                            },
+                            Some(Err(message)) => rsx! {
+                                div {
+                                    style: "text-align: center; padding: 20px; opacity: 0.6; color: {text_color};",
+                                    "⚠️ {message}"
+                                }
+                            },
+                            Some(Ok(hits)) if hits.is_empty() => rsx! {
+                                div {
+                                    style: "text-align: center; padding: 20px; opacity: 0.6; color: {text_color};",
+                                    "該当する実行履歴がありません"
+                                }
+                            },
+                            Some(Ok(hits)) => rsx! {
+                                for hit in hits {
+                                    div {
+                                        key: "{hit.history.id}",
+                                        style: "background: {textarea_bg}; padding: 12px 15px; border-radius: 4px; border: 1px solid {border_color}; margin-bottom: 10px;",
+
+                                        div {
+                                            style: "font-weight: bold; color: {text_color}; margin-bottom: 4px; font-size: 0.95rem; word-break: break-word;",
+                                            "💬 " {hit.history.command.clone()}
+                                        }
+
+                                        div {
+                                            style: "font-size: 0.8rem; opacity: 0.8; color: {text_color}; margin-bottom: 5px;",
+                                            "📅 実行: " {hit.history.executed_at.clone()} " | 🌿 Branch: " {hit.history.branch.clone()} " | 結果: "
+                                            span {
+                                                style: match hit.history.status { ExecutionStatus::Success => "color: #16a34a; font-weight: bold;", ExecutionStatus::Cancelled => "color: #6b7280; font-weight: bold;", _ => "color: #dc2626; font-weight: bold;" },
+                                                {hit.history.status.to_string()}
+                                            }
+                                        }
+
+                                        pre {
+                                            style: "background: {card_bg}; padding: 8px 10px; border-radius: 4px; border: 1px solid {border_color}; font-family: monospace; white-space: pre-wrap; color: {text_color}; font-size: 0.75rem; margin: 5px 0 0 0;",
+                                            for (text, matched) in snippet_fragments(&hit.snippet) {
+                                                if matched {
+                                                    mark { "{text}" }
+                                                } else {
+                                                    "{text}"
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                            },
+                        }}
+                    }
+                } else if execution_history().is_empty() && !is_executing() {
                     div {
                         style: "text-align: center; padding: 40px; opacity: 0.6; color: {text_color};",
                         "📈 実行履歴がありません"
@@ -1267,7 +1444,7 @@ pub fn app() -> Element {
                                             }
                                             " | 結果: "
                                             span {
-                                                style: if history.status == ExecutionStatus::Success { "color: #16a34a; font-weight: bold;" } else { "color: #dc2626; font-weight: bold;" },
+                                                style: match history.status { ExecutionStatus::Success => "color: #16a34a; font-weight: bold;", ExecutionStatus::Cancelled => "color: #6b7280; font-weight: bold;", _ => "color: #dc2626; font-weight: bold;" },
                                                 {history.status.to_string()}
                                             }
                                         }
@@ -1,33 +1,69 @@
 use anyhow::Result;
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
+use std::fs;
 use std::path::PathBuf;
-use tokio::fs;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub general: GeneralConfig,
     pub git: GitConfig,
     pub storage: StorageConfig,
+    #[serde(default)]
+    pub daemon: DaemonConfig,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GeneralConfig {
     pub default_mode: String,
     pub check_interval: u64,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GitConfig {
     pub enable_worktree: bool,
     pub default_branch: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StorageConfig {
     pub database_path: String,
 }
 
+/// デーモンの設定（SIGHUPで再読み込みされる）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DaemonConfig {
+    /// 終了時に実行中のコマンドを待つ秒数。過ぎたら中断する
+    pub shutdown_grace_period: u64,
+    /// 同時に実行するコマンドの最大数（同じworktreeでは常に1つずつ実行する）
+    pub max_concurrency: usize,
+    /// 前回のデーモンが実行中のまま終了したスケジュールの扱い
+    pub orphan_policy: OrphanPolicy,
+}
+
+/// 実行中のまま残ったスケジュールを起動時にどう扱うか
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrphanPolicy {
+    /// 失敗した実行として記録する
+    Fail,
+    /// 待機中に戻して再実行する
+    Requeue,
+    /// 1件ずつ確認する（端末がない場合は Fail と同じ）
+    Ask,
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        Self {
+            shutdown_grace_period: 60,
+            max_concurrency: 4,
+            orphan_policy: OrphanPolicy::Fail,
+        }
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
@@ -42,35 +78,36 @@ impl Default for Config {
             storage: StorageConfig {
                 database_path: default_database_path().to_string_lossy().to_string(),
             },
+            daemon: DaemonConfig::default(),
         }
     }
 }
 
 impl Config {
-    pub async fn load() -> Result<Self> {
+    pub fn load() -> Result<Self> {
         let config_path = config_file_path();
 
         if config_path.exists() {
-            let content = fs::read_to_string(&config_path).await?;
+            let content = fs::read_to_string(&config_path)?;
             let config: Config = toml::from_str(&content)?;
             Ok(config)
         } else {
             let config = Config::default();
-            config.save().await?;
+            config.save()?;
             Ok(config)
         }
     }
 
-    pub async fn save(&self) -> Result<()> {
+    pub fn save(&self) -> Result<()> {
         let config_path = config_file_path();
 
         // Create parent directory if it doesn't exist
         if let Some(parent) = config_path.parent() {
-            fs::create_dir_all(parent).await?;
+            fs::create_dir_all(parent)?;
         }
 
         let content = toml::to_string_pretty(self)?;
-        fs::write(&config_path, content).await?;
+        fs::write(&config_path, content)?;
         Ok(())
     }
 
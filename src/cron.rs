@@ -0,0 +1,186 @@
+use anyhow::{bail, Context, Result};
+use chrono::{Datelike, Duration, NaiveDateTime, Timelike};
+use std::str::FromStr;
+
+/// 次回実行時刻を探索する最大日数（存在しない日付指定で無限ループしないための上限）
+const MAX_SEARCH_DAYS: i64 = 366 * 8;
+
+const MONTH_NAMES: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// crontab形式のスケジュール式
+///
+/// 5フィールド（分 時 日 月 曜日）と、先頭に秒を加えた6フィールド形式、
+/// `@daily` などのショートカットに対応する。曜日は0(または7)が日曜日。
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronExpression {
+    minutes: u64,
+    hours: u32,
+    days_of_month: u32,
+    months: u16,
+    days_of_week: u8,
+    day_of_month_restricted: bool,
+    day_of_week_restricted: bool,
+}
+
+impl CronExpression {
+    /// 指定時刻より後（同じ分は含まない）の最初の実行時刻を返す
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let start = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+
+        for day_offset in 0..MAX_SEARCH_DAYS {
+            let date = start.date() + Duration::days(day_offset);
+            if !self.matches_date(date) {
+                continue;
+            }
+
+            let first_day = day_offset == 0;
+            for hour in 0..24 {
+                if self.hours & (1 << hour) == 0 || (first_day && hour < start.hour()) {
+                    continue;
+                }
+                for minute in 0..60 {
+                    if self.minutes & (1 << minute) == 0
+                        || (first_day && hour == start.hour() && minute < start.minute())
+                    {
+                        continue;
+                    }
+                    return date.and_hms_opt(hour, minute, 0);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn matches_date(&self, date: chrono::NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+
+        let dom = self.days_of_month & (1 << date.day()) != 0;
+        let dow = self.days_of_week & (1 << date.weekday().num_days_from_sunday()) != 0;
+
+        // 日と曜日の両方が指定されている場合はどちらかに一致すれば良い（crontabと同じ挙動）
+        match (self.day_of_month_restricted, self.day_of_week_restricted) {
+            (true, true) => dom || dow,
+            (true, false) => dom,
+            (false, true) => dow,
+            (false, false) => true,
+        }
+    }
+}
+
+impl FromStr for CronExpression {
+    type Err = anyhow::Error;
+
+    fn from_str(expression: &str) -> Result<Self> {
+        let expression = expression.trim();
+        let expanded = match expression.to_lowercase().as_str() {
+            "@yearly" | "@annually" => "0 0 1 1 *".to_string(),
+            "@monthly" => "0 0 1 * *".to_string(),
+            "@weekly" => "0 0 * * 0".to_string(),
+            "@daily" | "@midnight" => "0 0 * * *".to_string(),
+            "@hourly" => "0 * * * *".to_string(),
+            other if other.starts_with('@') => {
+                bail!("Unsupported cron shortcut '{expression}'")
+            }
+            _ => expression.to_string(),
+        };
+
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let fields = match fields.len() {
+            5 => &fields[..],
+            6 => {
+                // Schedules run at minute resolution, so the seconds field must allow :00
+                let seconds =
+                    parse_field(fields[0], 0, 59, &[]).context("Invalid seconds field")?;
+                if seconds & 1 == 0 {
+                    bail!("The seconds field must include 0 (schedules run at minute resolution)");
+                }
+                &fields[1..]
+            }
+            n => bail!("Invalid cron expression '{expression}': expected 5 or 6 fields, got {n}"),
+        };
+
+        let minutes = parse_field(fields[0], 0, 59, &[]).context("Invalid minute field")?;
+        let hours = parse_field(fields[1], 0, 23, &[]).context("Invalid hour field")?;
+        let days_of_month =
+            parse_field(fields[2], 1, 31, &[]).context("Invalid day-of-month field")?;
+        let months = parse_field(fields[3], 1, 12, &MONTH_NAMES).context("Invalid month field")?;
+        let mut days_of_week =
+            parse_field(fields[4], 0, 7, &WEEKDAY_NAMES).context("Invalid day-of-week field")?;
+
+        // 7 is an alias for Sunday
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week & !(1 << 7)) | 1;
+        }
+
+        Ok(Self {
+            minutes,
+            hours: hours as u32,
+            days_of_month: days_of_month as u32,
+            months: months as u16,
+            days_of_week: days_of_week as u8,
+            day_of_month_restricted: !fields[2].starts_with('*'),
+            day_of_week_restricted: !fields[4].starts_with('*'),
+        })
+    }
+}
+
+/// 1つのフィールドを解析し、許可される値をビットマスクで返す
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64> {
+    let mut mask = 0u64;
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .with_context(|| format!("Invalid step '{step}'"))?;
+                if step == 0 {
+                    bail!("Step must be greater than 0");
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (
+                parse_value(start, min, names)?,
+                parse_value(end, min, names)?,
+            )
+        } else {
+            let value = parse_value(range, min, names)?;
+            // "5/15" means "from 5 to the end, every 15"
+            (value, if part.contains('/') { max } else { value })
+        };
+
+        if start < min || end > max || start > end {
+            bail!("Value out of range in '{part}' (allowed {min}-{max})");
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            mask |= 1 << value;
+        }
+    }
+
+    Ok(mask)
+}
+
+fn parse_value(value: &str, min: u32, names: &[&str]) -> Result<u32> {
+    if let Some(index) = names
+        .iter()
+        .position(|name| value.eq_ignore_ascii_case(name))
+    {
+        return Ok(index as u32 + min);
+    }
+    value
+        .parse()
+        .with_context(|| format!("Invalid value '{value}'"))
+}
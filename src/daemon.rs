@@ -0,0 +1,1223 @@
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDateTime};
+use log::{error, info, warn};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tokio_util::task::TaskTracker;
+
+use crate::cli_commands::{execute_command_internal, CommandOutcome, ExecutionControl};
+use crate::config::{Config, OrphanPolicy};
+use crate::database::{Database, HistoryFilter, OutputFilter};
+use crate::git;
+use crate::live_output::{LiveOutputs, OutputStream, RunningExecution};
+use crate::models::{
+    DependencyFailurePolicy, ExecutionHistory, ExecutionProcess, ExecutionStatus, ExecutionType,
+    MisfirePolicy, OutputLine, Schedule, ScheduleCondition, ScheduleStatus,
+};
+use crate::usage_limit;
+
+/// How often the daemon looks for executions `kill` asked it to stop
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Most output lines written to the database in one transaction
+const OUTPUT_BATCH_SIZE: usize = 500;
+
+/// Daemon settings taken from the configuration file and the command line
+#[derive(Debug, Clone, PartialEq)]
+pub struct DaemonSettings {
+    pub check_interval: Duration,
+    pub grace_period: Duration,
+    /// Only read at startup
+    pub max_concurrency: usize,
+}
+
+impl DaemonSettings {
+    /// `interval` is the `--interval` option, which wins over the configuration file
+    pub fn from_config(config: &Config, interval: Option<u64>) -> Self {
+        Self {
+            check_interval: Duration::from_secs(
+                interval.unwrap_or(config.general.check_interval).max(1),
+            ),
+            grace_period: Duration::from_secs(config.daemon.shutdown_grace_period),
+            max_concurrency: config.daemon.max_concurrency,
+        }
+    }
+}
+
+/// Limits on what runs at the same time: a bounded number of worker slots and
+/// one execution per worktree
+#[derive(Clone)]
+pub struct WorkerPool {
+    slots: Arc<Semaphore>,
+    worktrees: Arc<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>>,
+    claimed: Arc<Mutex<HashSet<String>>>,
+}
+
+impl WorkerPool {
+    pub fn new(max_concurrency: usize) -> Self {
+        Self {
+            slots: Arc::new(Semaphore::new(max_concurrency.max(1))),
+            worktrees: Arc::default(),
+            claimed: Arc::default(),
+        }
+    }
+
+    /// Mark a schedule as queued or running. Returns `None` if it already is.
+    pub fn claim(&self, schedule_id: &str) -> Option<ScheduleClaim> {
+        let newly_claimed = self.claimed.lock().unwrap().insert(schedule_id.to_string());
+
+        newly_claimed.then(|| ScheduleClaim {
+            schedule_id: schedule_id.to_string(),
+            claimed: self.claimed.clone(),
+        })
+    }
+
+    fn worktree(&self, execution_path: &str) -> Arc<tokio::sync::Mutex<()>> {
+        self.worktrees
+            .lock()
+            .unwrap()
+            .entry(execution_path.to_string())
+            .or_default()
+            .clone()
+    }
+}
+
+/// A schedule that is queued or running. Released when dropped.
+pub struct ScheduleClaim {
+    schedule_id: String,
+    claimed: Arc<Mutex<HashSet<String>>>,
+}
+
+impl Drop for ScheduleClaim {
+    fn drop(&mut self) {
+        self.claimed.lock().unwrap().remove(&self.schedule_id);
+    }
+}
+
+/// Shared state of a running daemon, used by the schedule checker and the API
+#[derive(Clone)]
+pub struct DaemonState {
+    pub db: Database,
+    pub outputs: LiveOutputs,
+    pub workers: WorkerPool,
+    pub settings: Arc<RwLock<DaemonSettings>>,
+    /// Recorded as `claimed_by` on the schedules this daemon runs
+    pub daemon_id: String,
+    /// Cancelled when the daemon should stop picking up new work
+    pub shutdown: CancellationToken,
+    /// Cancelled when running executions should be killed
+    pub interrupt: CancellationToken,
+    /// Queued and running executions, so that shutdown can wait for them
+    pub tasks: TaskTracker,
+}
+
+impl DaemonState {
+    pub fn new(db: Database, settings: DaemonSettings) -> Self {
+        Self {
+            db,
+            outputs: LiveOutputs::default(),
+            workers: WorkerPool::new(settings.max_concurrency),
+            settings: Arc::new(RwLock::new(settings)),
+            daemon_id: format!("pid:{}", std::process::id()),
+            shutdown: CancellationToken::new(),
+            interrupt: CancellationToken::new(),
+            tasks: TaskTracker::new(),
+        }
+    }
+
+    pub fn settings(&self) -> DaemonSettings {
+        self.settings.read().unwrap().clone()
+    }
+}
+
+/// Generate an id for a new execution history entry
+pub fn new_execution_id() -> String {
+    format!(
+        "exec_{}",
+        chrono::Utc::now().timestamp_nanos_opt().unwrap_or(0)
+    )
+}
+
+/// Whether a pending schedule's time has come
+pub fn is_due(schedule: &Schedule, now: DateTime<Local>) -> bool {
+    schedule
+        .scheduled_time
+        .as_deref()
+        .and_then(|time| NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M").ok())
+        .and_then(|time| time.and_local_timezone(Local).earliest())
+        .is_some_and(|scheduled| now >= scheduled)
+}
+
+/// Dispatch every pending schedule whose time has come to the worker pool
+/// (one daemon tick). Schedules that are still queued or running from an
+/// earlier tick are left alone.
+pub async fn run_due_schedules(state: &DaemonState) -> Result<()> {
+    // Rate limited schedules wait for their reset time like pending ones
+    let mut schedules = state
+        .db
+        .get_schedules(Some(ScheduleStatus::Pending), None)
+        .await?;
+    schedules.extend(
+        state
+            .db
+            .get_schedules(Some(ScheduleStatus::RateLimited), None)
+            .await?,
+    );
+
+    let now = Local::now();
+
+    for schedule in schedules {
+        if !is_due(&schedule, now) {
+            continue;
+        }
+        if state.shutdown.is_cancelled() {
+            break;
+        }
+        let Some(claim) = state.workers.claim(&schedule.id) else {
+            continue;
+        };
+
+        match dependency_state(&state.db, &schedule).await? {
+            DependencyState::Ready => {}
+            DependencyState::Waiting => continue,
+            DependencyState::Failed(parent) => match schedule.on_dependency_failure {
+                DependencyFailurePolicy::Run => {}
+                DependencyFailurePolicy::Wait => continue,
+                DependencyFailurePolicy::Skip => {
+                    let reason = format!("Skipped: dependency {parent} did not succeed");
+                    skip_schedule_run(&state.db, schedule, &reason).await?;
+                    continue;
+                }
+            },
+        }
+
+        if let Some(late) = missed_grace(&schedule, now) {
+            let reason = format!(
+                "Skipped: scheduled for {}, {} late (grace {})",
+                schedule.scheduled_time.as_deref().unwrap_or("-"),
+                crate::utils::format_duration(late),
+                crate::utils::format_duration(schedule.misfire_grace())
+            );
+            skip_schedule_run(&state.db, schedule, &reason).await?;
+            continue;
+        }
+
+        info!(
+            "schedule due: id={} scheduled_time={} command={:?}",
+            schedule.id,
+            schedule.scheduled_time.as_deref().unwrap_or("-"),
+            schedule.command
+        );
+
+        dispatch(state, claim, schedule, new_execution_id(), RunTrigger::Due);
+    }
+
+    Ok(())
+}
+
+/// How far past the grace window a late schedule is, for schedules whose
+/// misfire policy skips late runs
+fn missed_grace(schedule: &Schedule, now: DateTime<Local>) -> Option<chrono::Duration> {
+    if schedule.misfire_policy != MisfirePolicy::Skip {
+        return None;
+    }
+    let late = now.naive_local() - schedule.scheduled_at()?;
+    (late > schedule.misfire_grace()).then_some(late)
+}
+
+/// How long a condition command may run before it counts as false
+const CONDITION_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The first condition of a schedule that does not hold, checked in the
+/// directory the schedule would run in
+async fn unmet_condition<'a>(
+    state: &DaemonState,
+    schedule: &'a Schedule,
+) -> Option<&'a ScheduleCondition> {
+    let execution_path = resolve_execution_path(schedule);
+
+    for condition in &schedule.conditions {
+        let holds = match condition {
+            ScheduleCondition::NewCommits => {
+                match git::get_head_commit_in_directory(&execution_path) {
+                    Some(head) => schedule.last_commit.as_ref() != Some(&head),
+                    None => false,
+                }
+            }
+            ScheduleCondition::FileExists { path } => std::path::Path::new(&execution_path)
+                .join(crate::utils::expand_path(path))
+                .exists(),
+            ScheduleCondition::Command { command } => {
+                let outcome = execute_command_internal(
+                    command,
+                    true,
+                    &execution_path,
+                    false,
+                    false,
+                    ExecutionControl {
+                        interrupt: Some(&state.shutdown),
+                        timeout: Some(CONDITION_TIMEOUT),
+                        ..Default::default()
+                    },
+                )
+                .await;
+                match outcome {
+                    Ok(outcome) => outcome.status == ExecutionStatus::Success,
+                    Err(e) => {
+                        warn!(
+                            "condition command failed to start: id={} command={command:?} error={e:#}",
+                            schedule.id
+                        );
+                        false
+                    }
+                }
+            }
+        };
+
+        if !holds {
+            return Some(condition);
+        }
+    }
+
+    None
+}
+
+/// Where a schedule stands with the schedules it depends on
+#[derive(Debug, PartialEq)]
+pub enum DependencyState {
+    /// Every dependency has completed successfully
+    Ready,
+    /// A dependency has not finished yet
+    Waiting,
+    /// Every dependency has finished but this one failed, was cancelled or was deleted
+    Failed(String),
+}
+
+/// A one-shot parent counts once it has finished. A recurring parent that is
+/// still repeating goes back to `pending` after every run, so its latest run
+/// since the schedule's own time counts instead.
+pub async fn dependency_state(db: &Database, schedule: &Schedule) -> Result<DependencyState> {
+    let mut failed = None;
+    for parent_id in &schedule.depends_on {
+        match db.get_schedule(parent_id).await? {
+            Some(parent) => match parent.status {
+                ScheduleStatus::Completed => {}
+                ScheduleStatus::Failed | ScheduleStatus::Cancelled => {
+                    failed.get_or_insert_with(|| parent_id.clone());
+                }
+                _ if parent.is_recurring() => {
+                    match latest_parent_run(db, parent_id, schedule).await? {
+                        Some(ExecutionStatus::Success) => {}
+                        Some(_) => {
+                            failed.get_or_insert_with(|| parent_id.clone());
+                        }
+                        None => return Ok(DependencyState::Waiting),
+                    }
+                }
+                _ => return Ok(DependencyState::Waiting),
+            },
+            None => {
+                failed.get_or_insert_with(|| parent_id.clone());
+            }
+        }
+    }
+
+    Ok(match failed {
+        Some(parent) => DependencyState::Failed(parent),
+        None => DependencyState::Ready,
+    })
+}
+
+/// Status of the latest run of a recurring parent that started at or after
+/// the schedule's own time. Runs that were skipped or will run again
+/// (interrupted, rate limited) do not decide anything.
+async fn latest_parent_run(
+    db: &Database,
+    parent_id: &str,
+    schedule: &Schedule,
+) -> Result<Option<ExecutionStatus>> {
+    let since = schedule
+        .scheduled_at()
+        .and_then(|time| time.and_local_timezone(Local).earliest());
+    let runs = db
+        .get_execution_history(&HistoryFilter {
+            schedule_id: Some(parent_id.to_string()),
+            from: since,
+            ..Default::default()
+        })
+        .await?;
+
+    Ok(runs.into_iter().map(|run| run.status).find(|status| {
+        matches!(
+            status,
+            ExecutionStatus::Success
+                | ExecutionStatus::Failed
+                | ExecutionStatus::TimedOut
+                | ExecutionStatus::Cancelled
+        )
+    }))
+}
+
+/// Record a run as skipped and move the schedule on without running it.
+/// Skipped runs do not count towards `--count`.
+async fn skip_schedule_run(db: &Database, schedule: Schedule, reason: &str) -> Result<()> {
+    let history = ExecutionHistory {
+        id: new_execution_id(),
+        command: schedule.command.clone(),
+        executed_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        execution_type: ExecutionType::FromSchedule,
+        status: ExecutionStatus::Skipped,
+        output: reason.to_string(),
+        branch: schedule.branch.clone(),
+        execution_path: resolve_execution_path(&schedule),
+        claude_skip_permissions: schedule.claude_skip_permissions,
+        claude_continue_from_last: schedule.claude_continue_from_last,
+        schedule_id: Some(schedule.id.clone()),
+        attempt: None,
+        exit_code: None,
+        batch_id: None,
+        stdout: String::new(),
+        stderr: String::new(),
+        finished_at: None,
+        duration_ms: None,
+    };
+    db.create_execution_history(&history).await?;
+
+    info!(
+        "schedule skipped: id={} execution_id={} reason={reason:?}",
+        schedule.id, history.id
+    );
+
+    if let Some(next_time) = schedule.next_occurrence(Local::now().naive_local()) {
+        db.update_scheduled_time(&schedule.id, &next_time).await?;
+        info!("schedule rearmed: id={} next_run={next_time}", schedule.id);
+    } else {
+        db.update_schedule_status(&schedule.id, ScheduleStatus::Failed)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Why a schedule is run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RunTrigger {
+    /// Its scheduled time has come
+    Due,
+    /// Started through the API. Recurring schedules keep their timing.
+    Manual,
+}
+
+/// Run a schedule on the worker pool in the background
+pub fn dispatch(
+    state: &DaemonState,
+    claim: ScheduleClaim,
+    schedule: Schedule,
+    execution_id: String,
+    trigger: RunTrigger,
+) {
+    let state = state.clone();
+    state.tasks.clone().spawn(async move {
+        let _claim = claim;
+        let schedule_id = schedule.id.clone();
+        let result = match trigger {
+            RunTrigger::Due => run_due_schedule(&state, schedule, &execution_id).await,
+            RunTrigger::Manual => run_schedule(&state, schedule, &execution_id, trigger).await,
+        };
+        if let Err(e) = result {
+            error!("failed to run schedule: id={schedule_id} error={e:#}");
+        }
+    });
+}
+
+/// Run a due schedule if its conditions hold. Condition commands can take a
+/// while, so they are checked here rather than in the tick that found the
+/// schedule due, which would hold up every other due schedule.
+async fn run_due_schedule(
+    state: &DaemonState,
+    schedule: Schedule,
+    execution_id: &str,
+) -> Result<()> {
+    if let Some(condition) = unmet_condition(state, &schedule).await {
+        // A condition command cut short by the shutdown says nothing
+        if state.shutdown.is_cancelled() {
+            return Ok(());
+        }
+        let reason = format!("Skipped: condition false ({condition})");
+        return skip_schedule_run(&state.db, schedule, &reason).await;
+    }
+
+    run_schedule(state, schedule, execution_id, RunTrigger::Due).await
+}
+
+/// Wait for a worker slot and the schedule's worktree, run it (retrying
+/// failed attempts as configured) and update the schedule. Nothing runs if the
+/// daemon starts shutting down while waiting.
+async fn run_schedule(
+    state: &DaemonState,
+    mut schedule: Schedule,
+    execution_id: &str,
+    trigger: RunTrigger,
+) -> Result<()> {
+    let execution_path = resolve_execution_path(&schedule);
+    let mut execution_id = execution_id.to_string();
+    let mut attempt = 1;
+
+    let (history, limit_resets_at) = loop {
+        let Some(worker) = acquire_worker(state, &execution_path).await? else {
+            if attempt > 1 {
+                release_schedule(state, &schedule).await?;
+            }
+            return Ok(());
+        };
+
+        // The lease lets the next daemon find runs this one never finished.
+        // It is held across retries. The schedule is read again first, as it
+        // may have been edited, cancelled or deleted while it was queued. A due
+        // run is only claimed for the time it was found due at, so another
+        // process that already ran it is not followed by a second run.
+        if attempt == 1 {
+            let due_at = schedule.scheduled_time.clone();
+            match state.db.get_schedule(&schedule.id).await? {
+                Some(current) => schedule = current,
+                None => {
+                    info!("schedule deleted before it ran: id={}", schedule.id);
+                    return Ok(());
+                }
+            }
+            let claimed = match trigger {
+                RunTrigger::Due => {
+                    state
+                        .db
+                        .claim_due_schedule(&schedule.id, &state.daemon_id, due_at.as_deref())
+                        .await?
+                }
+                RunTrigger::Manual => {
+                    state
+                        .db
+                        .claim_schedule(&schedule.id, &state.daemon_id)
+                        .await?
+                }
+            };
+            if !claimed {
+                warn!(
+                    "schedule not claimed: id={} status={}",
+                    schedule.id,
+                    schedule.status.to_db_string()
+                );
+                return Ok(());
+            }
+        }
+
+        let (history, limit_resets_at) =
+            match execute_schedule(state, &schedule, &execution_id, attempt).await {
+                Ok(run) => run,
+                Err(e) => {
+                    release_schedule(state, &schedule).await?;
+                    return Err(e);
+                }
+            };
+        drop(worker);
+
+        if !schedule.should_retry(&history, attempt) {
+            break (history, limit_resets_at);
+        }
+
+        // Back off without holding a worker, so other schedules can run
+        let delay = schedule.retry_delay(attempt);
+        info!(
+            "retry scheduled: id={} execution_id={execution_id} attempt={attempt} delay={}s",
+            schedule.id,
+            delay.as_secs()
+        );
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = state.shutdown.cancelled() => {
+                release_schedule(state, &schedule).await?;
+                return Ok(());
+            }
+        }
+
+        attempt += 1;
+        execution_id = new_execution_id();
+    };
+
+    // A run stopped by the usage limit does not count, it runs again once
+    // the limit resets
+    if let Some(resets_at) = limit_resets_at {
+        return requeue_after_limit(state, &schedule, &history, resets_at).await;
+    }
+
+    match history.status {
+        // Neither does an interrupted run, the schedule goes back to how it
+        // was and runs again when the daemon is back
+        ExecutionStatus::Interrupted => release_schedule(state, &schedule).await?,
+        _ if trigger == RunTrigger::Manual && schedule.is_recurring() => {
+            release_schedule(state, &schedule).await?
+        }
+        // Cancelling the run of a one-shot schedule cancels the schedule,
+        // a recurring schedule carries on with its next occurrence
+        ExecutionStatus::Cancelled if !schedule.is_recurring() => {
+            state
+                .db
+                .update_schedule_status(&schedule.id, ScheduleStatus::Cancelled)
+                .await?;
+            info!("schedule cancelled: id={}", schedule.id);
+        }
+        status => {
+            let success = status == ExecutionStatus::Success;
+            finish_schedule_run(&state.db, &mut schedule, success).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Queue a schedule stopped by the usage limit for the minute after the limit resets
+async fn requeue_after_limit(
+    state: &DaemonState,
+    schedule: &Schedule,
+    history: &ExecutionHistory,
+    resets_at: NaiveDateTime,
+) -> Result<()> {
+    let next_time = (resets_at + chrono::Duration::minutes(1))
+        .format("%Y-%m-%dT%H:%M")
+        .to_string();
+
+    state
+        .db
+        .update_scheduled_time(&schedule.id, &next_time)
+        .await?;
+    state
+        .db
+        .update_schedule_status(&schedule.id, ScheduleStatus::RateLimited)
+        .await?;
+
+    info!(
+        "schedule rate limited: id={} execution_id={} resets_at={} next_run={next_time}",
+        schedule.id,
+        history.id,
+        resets_at.format("%Y-%m-%d %H:%M:%S")
+    );
+    Ok(())
+}
+
+/// A worker slot plus exclusive use of a worktree
+type Worker = (
+    tokio::sync::OwnedMutexGuard<()>,
+    tokio::sync::OwnedSemaphorePermit,
+);
+
+/// Wait for the worktree and then a slot, or `None` if the daemon starts
+/// shutting down first
+async fn acquire_worker(state: &DaemonState, execution_path: &str) -> Result<Option<Worker>> {
+    // Take the worktree before a slot, so that a run waiting for its
+    // worktree does not keep a slot from other worktrees
+    let worktree = tokio::select! {
+        guard = state.workers.worktree(execution_path).lock_owned() => guard,
+        _ = state.shutdown.cancelled() => return Ok(None),
+    };
+    let slot = tokio::select! {
+        permit = state.workers.slots.clone().acquire_owned() => permit?,
+        _ = state.shutdown.cancelled() => return Ok(None),
+    };
+
+    Ok(Some((worktree, slot)))
+}
+
+/// Put a claimed schedule back to the status it had before this run
+async fn release_schedule(state: &DaemonState, schedule: &Schedule) -> Result<()> {
+    state
+        .db
+        .update_schedule_status(&schedule.id, schedule.status.clone())
+        .await
+}
+
+/// Directory a schedule runs in: its branch's worktree when there is one
+pub fn resolve_execution_path(schedule: &Schedule) -> String {
+    if git::is_git_repository(&schedule.execution_path) {
+        git::get_worktree_path_in_directory(&schedule.execution_path, &schedule.branch)
+    } else {
+        schedule.execution_path.clone()
+    }
+}
+
+/// Execute a schedule once and record the result in the execution history,
+/// along with when the usage limit resets if the run was stopped by it
+pub async fn execute_schedule(
+    state: &DaemonState,
+    schedule: &Schedule,
+    execution_id: &str,
+    attempt: u32,
+) -> Result<(ExecutionHistory, Option<NaiveDateTime>)> {
+    let execution_path = resolve_execution_path(schedule);
+    let executed_at = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+
+    info!(
+        "command started: execution_id={execution_id} schedule_id={} attempt={attempt} branch={} path={execution_path} mode={}",
+        schedule.id,
+        schedule.branch,
+        if schedule.is_shell_mode { "shell" } else { "claude" }
+    );
+
+    let live_output = state.outputs.start(RunningExecution {
+        id: execution_id.to_string(),
+        command: schedule.command.clone(),
+        branch: schedule.branch.clone(),
+        started_at: executed_at.clone(),
+        pid: None,
+    });
+
+    // Other processes find the run here to stop it (`kill`)
+    state
+        .db
+        .start_execution_process(&ExecutionProcess {
+            execution_id: execution_id.to_string(),
+            schedule_id: Some(schedule.id.clone()),
+            command: schedule.command.clone(),
+            pid: None,
+            daemon_id: state.daemon_id.clone(),
+            started_at: executed_at.clone(),
+            cancel_requested: false,
+        })
+        .await?;
+    let db = state.db.clone();
+    let record_pid = |pid: u32| {
+        live_output.set_pid(pid);
+        let db = db.clone();
+        let execution_id = execution_id.to_string();
+        tokio::spawn(async move {
+            if let Err(e) = db.set_execution_pid(&execution_id, pid).await {
+                warn!("pid not recorded: execution_id={execution_id} error={e:#}");
+            }
+        });
+    };
+    let cancel = live_output.cancellation();
+    // Other processes follow the output in the database (`logs --follow`)
+    let (line_sender, recorder) = spawn_output_recorder(state.db.clone(), execution_id.to_string());
+    let record_line = move |stream: OutputStream, text: &str| {
+        // The recorder only stops once this closure is dropped
+        let _ = line_sender.send(OutputLine {
+            line: 0,
+            stream: stream.as_str().to_string(),
+            text: text.to_string(),
+            written_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        });
+    };
+
+    // A run after a usage limit can pick up the session the limit cut off
+    let continue_from_last = schedule.claude_continue_from_last
+        || (schedule.continue_after_limit && schedule.status == ScheduleStatus::RateLimited);
+
+    let mut outcome = execute_command_internal(
+        &schedule.command,
+        schedule.is_shell_mode,
+        &execution_path,
+        schedule.claude_skip_permissions,
+        continue_from_last,
+        ExecutionControl {
+            live_output: Some(&live_output),
+            on_line: Some(&record_line),
+            on_spawn: Some(&record_pid),
+            interrupt: Some(&state.interrupt),
+            cancel: Some(&cancel),
+            timeout: schedule
+                .timeout_seconds
+                .map(|seconds| Duration::from_secs(seconds.into())),
+        },
+    )
+    .await
+    // A command that cannot even be spawned is recorded as a failed run
+    // instead of stopping the daemon
+    .unwrap_or_else(|e| CommandOutcome::failed(&e));
+
+    // Every line is in the database before the run shows up as finished
+    drop(record_line);
+    if let Err(e) = recorder.await {
+        warn!("output recorder failed: execution_id={execution_id} error={e}");
+    }
+    state.db.finish_execution_process(execution_id).await?;
+
+    // Only Claude reports the usage limit, and only by failing
+    let limit_resets_at = (outcome.status == ExecutionStatus::Failed && !schedule.is_shell_mode)
+        .then(|| {
+            usage_limit::detect_in_failed_run(
+                &outcome.stdout,
+                &outcome.stderr,
+                Local::now().naive_local(),
+            )
+        })
+        .flatten();
+    if limit_resets_at.is_some() {
+        outcome.status = ExecutionStatus::RateLimited;
+    }
+
+    let history = ExecutionHistory {
+        id: execution_id.to_string(),
+        command: schedule.command.clone(),
+        executed_at,
+        execution_type: ExecutionType::FromSchedule,
+        status: outcome.status,
+        output: outcome.output,
+        branch: schedule.branch.clone(),
+        execution_path,
+        claude_skip_permissions: schedule.claude_skip_permissions,
+        claude_continue_from_last: continue_from_last,
+        schedule_id: Some(schedule.id.clone()),
+        attempt: Some(attempt),
+        exit_code: outcome.exit_code,
+        batch_id: None,
+        stdout: outcome.stdout,
+        stderr: outcome.stderr,
+        finished_at: Some(Local::now().format("%Y-%m-%d %H:%M:%S").to_string()),
+        duration_ms: Some(outcome.duration.as_millis() as u64),
+    };
+
+    info!(
+        "command finished: execution_id={execution_id} status={} exit_code={} duration={:.1}s",
+        history.status.to_db_string(),
+        history
+            .exit_code
+            .map_or_else(|| "-".to_string(), |code| code.to_string()),
+        outcome.duration.as_secs_f64()
+    );
+
+    state.db.create_execution_history(&history).await?;
+    live_output.finish(history.status.clone());
+
+    Ok((history, limit_resets_at))
+}
+
+/// Write the lines of a running command to the database in batches as they
+/// arrive. The task ends once the sender has been dropped and everything sent
+/// has been written.
+fn spawn_output_recorder(
+    db: Database,
+    execution_id: String,
+) -> (mpsc::UnboundedSender<OutputLine>, JoinHandle<()>) {
+    let (sender, mut receiver) = mpsc::unbounded_channel::<OutputLine>();
+    let recorder = tokio::spawn(async move {
+        let mut next_line = 1;
+        while let Some(first) = receiver.recv().await {
+            let mut batch = vec![first];
+            while batch.len() < OUTPUT_BATCH_SIZE {
+                match receiver.try_recv() {
+                    Ok(line) => batch.push(line),
+                    Err(_) => break,
+                }
+            }
+            for line in &mut batch {
+                line.line = next_line;
+                next_line += 1;
+            }
+
+            if let Err(e) = db.append_output_lines(&execution_id, &batch).await {
+                warn!("output not recorded: execution_id={execution_id} error={e:#}");
+            }
+        }
+    });
+
+    (sender, recorder)
+}
+
+/// Count a finished run and either re-arm the schedule or mark it done
+pub async fn finish_schedule_run(
+    db: &Database,
+    schedule: &mut Schedule,
+    success: bool,
+) -> Result<()> {
+    db.increment_run_count(&schedule.id).await?;
+    schedule.run_count += 1;
+
+    // The next new_commits check compares against the HEAD after this run,
+    // so commits made by the run itself do not trigger it again
+    if schedule.conditions.contains(&ScheduleCondition::NewCommits) {
+        if let Some(head) = git::get_head_commit_in_directory(&resolve_execution_path(schedule)) {
+            db.update_last_commit(&schedule.id, &head).await?;
+            schedule.last_commit = Some(head);
+        }
+    }
+
+    // Recurring schedules are re-armed for their next occurrence until
+    // they run out of runs, one-shot schedules are finished. With run_all the
+    // next occurrence follows the one just run, so missed occurrences are
+    // still due and run one after another.
+    let now = Local::now().naive_local();
+    let after = match schedule.misfire_policy {
+        MisfirePolicy::RunAll => schedule.scheduled_at().map_or(now, |time| time.min(now)),
+        _ => now,
+    };
+    if let Some(next_time) = schedule.next_occurrence(after) {
+        db.update_scheduled_time(&schedule.id, &next_time).await?;
+        db.update_schedule_status(&schedule.id, ScheduleStatus::Pending)
+            .await?;
+        info!("schedule rearmed: id={} next_run={next_time}", schedule.id);
+    } else {
+        let new_status = if success {
+            ScheduleStatus::Completed
+        } else {
+            ScheduleStatus::Failed
+        };
+
+        info!(
+            "schedule finished: id={} status={} runs={}",
+            schedule.id,
+            new_status.to_db_string(),
+            schedule.run_count
+        );
+        db.update_schedule_status(&schedule.id, new_status).await?;
+    }
+
+    Ok(())
+}
+
+/// Stop the executions that `kill` asked this daemon to stop, until shutdown
+pub async fn watch_cancel_requests(state: DaemonState) {
+    // A request stays in the database until its command has exited
+    let mut handled = HashSet::new();
+    loop {
+        tokio::select! {
+            _ = state.shutdown.cancelled() => return,
+            _ = tokio::time::sleep(CANCEL_POLL_INTERVAL) => {}
+        }
+
+        match state.db.get_cancel_requests(&state.daemon_id).await {
+            Ok(execution_ids) => {
+                handled.retain(|execution_id| execution_ids.contains(execution_id));
+                for execution_id in execution_ids {
+                    if !handled.contains(&execution_id) && state.outputs.cancel(&execution_id) {
+                        info!("execution cancel requested: execution_id={execution_id}");
+                        handled.insert(execution_id);
+                    }
+                }
+            }
+            Err(e) => error!("cancel request check failed: error={e:#}"),
+        }
+    }
+}
+
+/// Record the running executions of daemons that are gone as interrupted.
+/// Commands they left behind are kept, so that `kill` can still stop them.
+pub async fn recover_orphaned_executions(db: &Database, daemon_id: &str) -> Result<()> {
+    for process in db.get_execution_processes().await? {
+        if process.daemon_id == daemon_id {
+            continue;
+        }
+        match process
+            .pid
+            .filter(|&pid| crate::daemonize::is_process_alive(pid, true))
+        {
+            Some(pid) => warn!(
+                "orphaned execution still running: execution_id={} pid={pid} daemon_id={}",
+                process.execution_id, process.daemon_id
+            ),
+            None => {
+                let reason = format!(
+                    "Interrupted: its daemon ({}) stopped while it was running",
+                    process.daemon_id
+                );
+                record_orphaned_execution(db, &process, ExecutionStatus::Interrupted, &reason)
+                    .await?;
+                info!(
+                    "orphaned execution recorded: execution_id={} status=interrupted",
+                    process.execution_id
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Finish an execution whose daemon is gone: write its history entry from the
+/// output recorded so far, followed by `reason`, and forget the process
+pub async fn record_orphaned_execution(
+    db: &Database,
+    process: &ExecutionProcess,
+    status: ExecutionStatus,
+    reason: &str,
+) -> Result<ExecutionHistory> {
+    let schedule = match &process.schedule_id {
+        Some(schedule_id) => db.get_schedule(schedule_id).await?,
+        None => None,
+    };
+
+    let mut output = String::new();
+    let mut stdout = String::new();
+    let mut stderr = String::new();
+    let lines = db
+        .get_output_lines(&process.execution_id, &OutputFilter::default())
+        .await?;
+    for line in &lines {
+        let stream = if line.stream == OutputStream::Stderr.as_str() {
+            &mut stderr
+        } else {
+            &mut stdout
+        };
+        for buffer in [&mut output, stream] {
+            buffer.push_str(&line.text);
+            buffer.push('\n');
+        }
+    }
+    for buffer in [&mut output, &mut stderr] {
+        buffer.push_str(reason);
+        buffer.push('\n');
+    }
+
+    let now = Local::now();
+    let duration_ms = NaiveDateTime::parse_from_str(&process.started_at, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .and_then(|started| (now.naive_local() - started).to_std().ok())
+        .map(|duration| duration.as_millis() as u64);
+
+    let history = ExecutionHistory {
+        id: process.execution_id.clone(),
+        command: process.command.clone(),
+        executed_at: process.started_at.clone(),
+        execution_type: if process.schedule_id.is_some() {
+            ExecutionType::FromSchedule
+        } else {
+            ExecutionType::Manual
+        },
+        status,
+        output,
+        branch: schedule
+            .as_ref()
+            .map_or_else(String::new, |schedule| schedule.branch.clone()),
+        execution_path: schedule
+            .as_ref()
+            .map_or_else(String::new, resolve_execution_path),
+        claude_skip_permissions: schedule
+            .as_ref()
+            .is_some_and(|schedule| schedule.claude_skip_permissions),
+        claude_continue_from_last: schedule
+            .as_ref()
+            .is_some_and(|schedule| schedule.claude_continue_from_last),
+        schedule_id: process.schedule_id.clone(),
+        attempt: None,
+        exit_code: None,
+        batch_id: None,
+        stdout,
+        stderr,
+        finished_at: Some(now.format("%Y-%m-%d %H:%M:%S").to_string()),
+        duration_ms,
+    };
+    db.create_execution_history(&history).await?;
+    db.finish_execution_process(&process.execution_id).await?;
+
+    Ok(history)
+}
+
+/// Whether the process that claimed a schedule (`pid:N`), such as the GUI, is
+/// still alive and may be running it right now
+fn claim_owner_alive(claimed_by: Option<&str>) -> bool {
+    claimed_by
+        .and_then(|owner| owner.strip_prefix("pid:"))
+        .and_then(|pid| pid.parse().ok())
+        .is_some_and(|pid| crate::daemonize::is_process_alive(pid, false))
+}
+
+/// Deal with schedules left `running` by a daemon that did not stop cleanly,
+/// before this daemon starts picking up due schedules. Schedules whose owner
+/// is still alive are left to it.
+pub async fn recover_orphaned_schedules(db: &Database, policy: OrphanPolicy) -> Result<()> {
+    let orphans = db
+        .get_schedules(Some(ScheduleStatus::Running), None)
+        .await?;
+
+    for mut schedule in orphans {
+        if claim_owner_alive(schedule.claimed_by.as_deref()) {
+            info!(
+                "running schedule still owned: id={} claimed_by={}",
+                schedule.id,
+                schedule.claimed_by.as_deref().unwrap_or("-")
+            );
+            continue;
+        }
+        let claimed_by = schedule
+            .claimed_by
+            .clone()
+            .unwrap_or_else(|| "-".to_string());
+        let started_at = schedule
+            .started_at
+            .clone()
+            .unwrap_or_else(|| Local::now().format("%Y-%m-%d %H:%M:%S").to_string());
+        warn!(
+            "orphaned schedule found: id={} claimed_by={claimed_by} started_at={started_at}",
+            schedule.id
+        );
+
+        let action = match policy {
+            OrphanPolicy::Ask => ask_orphan_action(&schedule, &claimed_by, &started_at)?,
+            policy => Some(policy),
+        };
+
+        match action {
+            Some(OrphanPolicy::Requeue) => {
+                db.update_schedule_status(&schedule.id, ScheduleStatus::Pending)
+                    .await?;
+                info!("orphaned schedule requeued: id={}", schedule.id);
+            }
+            Some(_) => {
+                let history = ExecutionHistory {
+                    id: new_execution_id(),
+                    command: schedule.command.clone(),
+                    executed_at: started_at.clone(),
+                    execution_type: ExecutionType::FromSchedule,
+                    status: ExecutionStatus::Failed,
+                    output: format!(
+                        "The daemon stopped while this schedule was running (claimed by {claimed_by} at {started_at})"
+                    ),
+                    branch: schedule.branch.clone(),
+                    execution_path: resolve_execution_path(&schedule),
+                    claude_skip_permissions: schedule.claude_skip_permissions,
+                    claude_continue_from_last: schedule.claude_continue_from_last,
+                    schedule_id: Some(schedule.id.clone()),
+                    attempt: None,
+                    exit_code: None,
+                    batch_id: None,
+                    stdout: String::new(),
+                    stderr: String::new(),
+                    finished_at: None,
+                    duration_ms: None,
+                };
+                db.create_execution_history(&history).await?;
+                info!(
+                    "orphaned schedule failed: id={} execution_id={}",
+                    schedule.id, history.id
+                );
+                finish_schedule_run(db, &mut schedule, false).await?;
+            }
+            None => info!("orphaned schedule left running: id={}", schedule.id),
+        }
+    }
+
+    Ok(())
+}
+
+/// Ask on the terminal what to do with an orphaned schedule. Without a
+/// terminal (e.g. with `--detach`) the run is failed.
+fn ask_orphan_action(
+    schedule: &Schedule,
+    claimed_by: &str,
+    started_at: &str,
+) -> Result<Option<OrphanPolicy>> {
+    use std::io::{BufRead, IsTerminal, Write};
+
+    if !std::io::stdin().is_terminal() {
+        warn!(
+            "no terminal to ask about orphaned schedule, failing it: id={}",
+            schedule.id
+        );
+        return Ok(Some(OrphanPolicy::Fail));
+    }
+
+    println!(
+        "⚠️ Schedule {} was still running when the daemon stopped (claimed by {claimed_by} at {started_at})",
+        schedule.id
+    );
+    println!("   Command: {}", schedule.command);
+
+    loop {
+        print!("   [r]equeue, [f]ail or [s]kip? ");
+        std::io::stdout().flush()?;
+
+        let mut answer = String::new();
+        if std::io::stdin().lock().read_line(&mut answer)? == 0 {
+            return Ok(Some(OrphanPolicy::Fail));
+        }
+        match answer.trim().to_lowercase().as_str() {
+            "r" | "requeue" => return Ok(Some(OrphanPolicy::Requeue)),
+            "f" | "fail" => return Ok(Some(OrphanPolicy::Fail)),
+            "s" | "skip" => return Ok(None),
+            _ => continue,
+        }
+    }
+}
+
+/// Re-read the configuration file and apply the settings that can change at runtime
+pub fn reload_settings(state: &DaemonState, interval: Option<u64>) {
+    match Config::load() {
+        Ok(config) => {
+            let settings = DaemonSettings::from_config(&config, interval);
+            info!(
+                "configuration reloaded: check_interval={}s grace_period={}s",
+                settings.check_interval.as_secs(),
+                settings.grace_period.as_secs()
+            );
+            *state.settings.write().unwrap() = settings;
+        }
+        Err(e) => error!("configuration reload failed, keeping current settings: error={e:#}"),
+    }
+}
+
+/// Handle signals until the daemon shuts down.
+///
+/// SIGHUP reloads the configuration. SIGTERM/SIGINT stop new work from being
+/// picked up and give running executions the grace period to finish before
+/// they are interrupted. A second SIGTERM/SIGINT interrupts them right away.
+#[cfg(unix)]
+pub async fn handle_signals(state: DaemonState, interval: Option<u64>) -> Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut hangup = signal(SignalKind::hangup())?;
+
+    let name = loop {
+        tokio::select! {
+            _ = terminate.recv() => break "SIGTERM",
+            _ = interrupt.recv() => break "SIGINT",
+            _ = hangup.recv() => reload_settings(&state, interval),
+        }
+    };
+
+    shut_down(&state, name, async {
+        tokio::select! {
+            _ = terminate.recv() => {}
+            _ = interrupt.recv() => {}
+        }
+    })
+    .await;
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub async fn handle_signals(state: DaemonState, _interval: Option<u64>) -> Result<()> {
+    tokio::signal::ctrl_c().await?;
+    shut_down(&state, "Ctrl-C", async {
+        let _ = tokio::signal::ctrl_c().await;
+    })
+    .await;
+
+    Ok(())
+}
+
+async fn shut_down(
+    state: &DaemonState,
+    signal: &str,
+    force: impl std::future::Future<Output = ()>,
+) {
+    let grace_period = state.settings().grace_period;
+    info!(
+        "shutdown requested: signal={signal} running={} grace_period={}s",
+        state.outputs.running().len(),
+        grace_period.as_secs()
+    );
+    state.shutdown.cancel();
+
+    tokio::select! {
+        _ = tokio::time::sleep(grace_period) => {
+            warn!("grace period expired, interrupting running executions");
+        }
+        _ = force => {
+            warn!("signal received again, interrupting running executions");
+        }
+    }
+    state.interrupt.cancel();
+}
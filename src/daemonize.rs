@@ -0,0 +1,157 @@
+use anyhow::{bail, Context, Result};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+/// PID file that stays exclusively locked for as long as the daemon runs.
+///
+/// The lock, not the file's existence, tells whether a daemon is running: a
+/// PID file left behind by a crashed daemon is unlocked and simply taken over.
+pub struct PidFile {
+    file: File,
+    path: PathBuf,
+}
+
+impl PidFile {
+    pub fn acquire(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .with_context(|| format!("Failed to open PID file {}", path.display()))?;
+
+        let mut previous = String::new();
+        file.read_to_string(&mut previous)?;
+        let previous_pid = previous.trim();
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => bail!(
+                "Another daemon is already running (PID {}, PID file {})",
+                if previous_pid.is_empty() {
+                    "unknown"
+                } else {
+                    previous_pid
+                },
+                path.display()
+            ),
+            Err(TryLockError::Error(e)) => {
+                return Err(e).with_context(|| format!("Failed to lock {}", path.display()))
+            }
+        }
+
+        // The lock is free, so no daemon holds the file. Its PID may have
+        // been reused by an unrelated process since.
+        if !previous_pid.is_empty() {
+            let state = if previous_pid
+                .parse()
+                .is_ok_and(|pid| is_process_alive(pid, false))
+            {
+                "is now used by another process"
+            } else {
+                "is no longer running"
+            };
+            eprintln!(
+                "⚠️ Taking over stale PID file {} (PID {previous_pid} {state})",
+                path.display()
+            );
+        }
+
+        Ok(Self {
+            file,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Record the current process id. Called again after detaching, since
+    /// the daemon then runs under a different PID.
+    pub fn write_pid(&mut self) -> Result<()> {
+        self.file.set_len(0)?;
+        self.file.rewind()?;
+        writeln!(self.file, "{}", std::process::id())?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Detach from the terminal: fork twice with a `setsid` in between, change to
+/// `/` and point the standard streams to `/dev/null`. Only the final daemon
+/// process returns, so set up logging to a file afterwards.
+///
+/// Must be called before any threads are started, which includes the tokio
+/// runtime.
+#[cfg(unix)]
+pub fn detach() -> Result<()> {
+    use nix::sys::wait::waitpid;
+    use nix::unistd::{chdir, dup2_stderr, dup2_stdin, dup2_stdout, fork, setsid, ForkResult};
+
+    let stdin = File::open("/dev/null")?;
+    let output = OpenOptions::new().write(true).open("/dev/null")?;
+
+    std::io::stdout().flush()?;
+
+    // SAFETY: no other threads exist yet (see above)
+    if let ForkResult::Parent { child } = unsafe { fork() }? {
+        // Wait for the intermediate child so the daemon PID is printed
+        // before the shell prompt comes back
+        waitpid(child, None)?;
+        std::process::exit(0);
+    }
+
+    setsid()?;
+
+    // Forking again means the daemon is not a session leader and can never
+    // acquire a controlling terminal
+    if let ForkResult::Parent { child } = unsafe { fork() }? {
+        println!("🚀 Claude Scheduler daemon started in the background (PID {child})");
+        std::process::exit(0);
+    }
+
+    chdir("/")?;
+    dup2_stdin(&stdin)?;
+    dup2_stdout(&output)?;
+    dup2_stderr(&output)?;
+
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn detach() -> Result<()> {
+    bail!("--detach is only supported on Unix. Use a service manager to run the daemon in the background.")
+}
+
+/// Whether a process with this PID exists. With `group` the process group
+/// led by `pid` is checked instead, which outlives its leader while anything
+/// it started is still running.
+#[cfg(unix)]
+pub fn is_process_alive(pid: u32, group: bool) -> bool {
+    use nix::errno::Errno;
+    use nix::sys::signal::{kill, killpg};
+    use nix::unistd::Pid;
+
+    let pid = Pid::from_raw(pid as i32);
+    let result = if group {
+        killpg(pid, None)
+    } else {
+        kill(pid, None)
+    };
+    // EPERM means it exists but belongs to someone else
+    !matches!(result, Err(Errno::ESRCH))
+}
+
+#[cfg(not(unix))]
+pub fn is_process_alive(_pid: u32, _group: bool) -> bool {
+    false
+}
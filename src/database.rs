@@ -1,16 +1,71 @@
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use chrono::{DateTime, Local};
-use sqlx::sqlite::SqlitePool;
+use sqlx::sqlite::{Sqlite, SqlitePool, SqliteRow};
+use sqlx::{Encode, QueryBuilder, Type};
 use std::path::Path;
+use std::time::Duration;
+
+use crate::live_output::OutputStream;
+use crate::migrations::{self, Migration, MigrationState};
+use crate::models::{
+    DependencyFailurePolicy, ExecutionHistory, ExecutionProcess, ExecutionStatus, ExecutionType,
+    MisfirePolicy, OutputLine, Schedule, ScheduleStatus, SearchHit,
+};
+
+/// Which lines `get_output_lines` returns
+#[derive(Debug, Clone, Default)]
+pub struct OutputFilter {
+    /// Only lines after this line number
+    pub after_line: u64,
+    pub stream: Option<OutputStream>,
+    /// "2024-01-01 10:00:00" format
+    pub since: Option<String>,
+    /// Only lines containing this text (case-sensitive)
+    pub contains: Option<String>,
+}
 
-use crate::models::{ExecutionHistory, ExecutionStatus, ExecutionType, Schedule, ScheduleStatus};
+/// Which entries `get_execution_history` returns; every field that is set
+/// has to match
+#[derive(Debug, Clone, Default)]
+pub struct HistoryFilter {
+    pub status: Option<ExecutionStatus>,
+    pub execution_type: Option<ExecutionType>,
+    pub branch: Option<String>,
+    pub schedule_id: Option<String>,
+    pub from: Option<DateTime<Local>>,
+    pub to: Option<DateTime<Local>>,
+    /// Runs from before durations were recorded never match
+    pub min_duration: Option<Duration>,
+    pub exit_code: Option<i32>,
+    pub limit: Option<usize>,
+}
 
+#[derive(Clone)]
 pub struct Database {
     pool: SqlitePool,
 }
 
 impl Database {
+    /// Open the database and apply pending migrations. A database migrated
+    /// by a newer claude-scheduler is refused.
     pub async fn new(path: &Path) -> Result<Self> {
+        let db = Self::connect(path).await?;
+        let applied = db
+            .migrate()
+            .await
+            .with_context(|| format!("Failed to migrate the database {}", path.display()))?;
+        if applied.is_empty() {
+            return Ok(db);
+        }
+
+        // Connections opened before the migration describe rows with the old
+        // columns, so start over with fresh ones
+        db.pool.close().await;
+        Self::connect(path).await
+    }
+
+    /// Open the database without migrating it
+    pub async fn connect(path: &Path) -> Result<Self> {
         // Create parent directory if it doesn't exist
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
@@ -19,74 +74,24 @@ impl Database {
         let database_url = format!("sqlite:{}?mode=rwc", path.display());
         let pool = SqlitePool::connect(&database_url).await?;
 
-        let db = Self { pool };
-        db.initialize().await?;
-        Ok(db)
+        Ok(Self { pool })
     }
 
-    async fn initialize(&self) -> Result<()> {
-        // Create schedules table
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS schedules (
-                id TEXT PRIMARY KEY,
-                command TEXT NOT NULL,
-                scheduled_time TEXT,
-                memo TEXT,
-                created_at TEXT NOT NULL,
-                status TEXT NOT NULL,
-                is_shell_mode INTEGER NOT NULL,
-                branch TEXT NOT NULL,
-                execution_path TEXT NOT NULL DEFAULT '.',
-                claude_skip_permissions INTEGER NOT NULL DEFAULT 0,
-                claude_continue_from_last INTEGER NOT NULL DEFAULT 0
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
-
-        // Create execution_history table
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS execution_history (
-                id TEXT PRIMARY KEY,
-                command TEXT NOT NULL,
-                executed_at TEXT NOT NULL,
-                execution_type TEXT NOT NULL,
-                status TEXT NOT NULL,
-                output TEXT NOT NULL,
-                branch TEXT NOT NULL,
-                execution_path TEXT NOT NULL DEFAULT '.',
-                claude_skip_permissions INTEGER NOT NULL DEFAULT 0,
-                claude_continue_from_last INTEGER NOT NULL DEFAULT 0
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
-
-        // Create configuration table
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS configuration (
-                key TEXT PRIMARY KEY,
-                value TEXT NOT NULL
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
+    /// Apply pending migrations and return them
+    pub async fn migrate(&self) -> Result<Vec<&'static Migration>> {
+        migrations::migrate(&self.pool).await
+    }
 
-        Ok(())
+    pub async fn migration_status(&self) -> Result<Vec<MigrationState>> {
+        migrations::status(&self.pool).await
     }
 
     // Schedule methods
     pub async fn create_schedule(&self, schedule: &Schedule) -> Result<()> {
         sqlx::query(
             r#"
-            INSERT INTO schedules (id, command, scheduled_time, memo, created_at, status, is_shell_mode, branch, execution_path, claude_skip_permissions, claude_continue_from_last)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO schedules (id, command, scheduled_time, memo, created_at, status, is_shell_mode, branch, execution_path, claude_skip_permissions, claude_continue_from_last, cron_expression, interval_minutes, max_runs, repeat_until, run_count, timeout_seconds, misfire_policy, misfire_grace_seconds, retry_max_attempts, retry_delay_seconds, retry_multiplier, retry_on_exit_codes, retry_on_output, continue_after_limit, depends_on, on_dependency_failure, conditions, last_commit)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(&schedule.id)
@@ -100,6 +105,24 @@ impl Database {
         .bind(&schedule.execution_path)
         .bind(schedule.claude_skip_permissions as i32)
         .bind(schedule.claude_continue_from_last as i32)
+        .bind(&schedule.cron_expression)
+        .bind(schedule.interval_minutes)
+        .bind(schedule.max_runs)
+        .bind(&schedule.repeat_until)
+        .bind(schedule.run_count)
+        .bind(schedule.timeout_seconds)
+        .bind(schedule.misfire_policy.to_db_string())
+        .bind(schedule.misfire_grace_seconds)
+        .bind(schedule.retry_max_attempts)
+        .bind(schedule.retry_delay_seconds)
+        .bind(schedule.retry_multiplier)
+        .bind(serde_json::to_string(&schedule.retry_on_exit_codes)?)
+        .bind(serde_json::to_string(&schedule.retry_on_output)?)
+        .bind(schedule.continue_after_limit as i32)
+        .bind(serde_json::to_string(&schedule.depends_on)?)
+        .bind(schedule.on_dependency_failure.to_db_string())
+        .bind(serde_json::to_string(&schedule.conditions)?)
+        .bind(&schedule.last_commit)
         .execute(&self.pool)
         .await?;
 
@@ -111,59 +134,121 @@ impl Database {
         status_filter: Option<ScheduleStatus>,
         limit: Option<usize>,
     ) -> Result<Vec<Schedule>> {
-        let mut query = "SELECT * FROM schedules".to_string();
-
+        let mut query = Select::from("schedules");
         if let Some(status) = status_filter {
-            query.push_str(&format!(" WHERE status = '{}'", status.to_db_string()));
+            query.filter("status =", status.to_db_string());
         }
+        query.order_by("created_at DESC").limit(limit);
 
-        query.push_str(" ORDER BY created_at DESC");
+        let rows = query.fetch_all(&self.pool).await?;
 
-        if let Some(limit) = limit {
-            query.push_str(&format!(" LIMIT {limit}"));
-        }
-
-        let rows = sqlx::query(&query).fetch_all(&self.pool).await?;
-
-        let schedules = rows
-            .into_iter()
-            .map(|row| Schedule {
-                id: sqlx::Row::get(&row, "id"),
-                command: sqlx::Row::get(&row, "command"),
-                scheduled_time: sqlx::Row::get(&row, "scheduled_time"),
-                _memo: sqlx::Row::get(&row, "memo"),
-                created_at: sqlx::Row::get(&row, "created_at"),
-                status: ScheduleStatus::from_string(&sqlx::Row::get::<String, _>(&row, "status")),
-                is_shell_mode: sqlx::Row::get::<i32, _>(&row, "is_shell_mode") != 0,
-                branch: sqlx::Row::get(&row, "branch"),
-                execution_path: sqlx::Row::try_get(&row, "execution_path").unwrap_or_else(|_| {
-                    std::env::current_dir()
-                        .unwrap_or_else(|_| std::path::PathBuf::from("."))
-                        .to_string_lossy()
-                        .to_string()
-                }),
-                claude_skip_permissions: sqlx::Row::try_get::<i32, _>(
-                    &row,
-                    "claude_skip_permissions",
-                )
-                .unwrap_or(0)
-                    != 0,
-                claude_continue_from_last: sqlx::Row::try_get::<i32, _>(
-                    &row,
-                    "claude_continue_from_last",
-                )
-                .unwrap_or(0)
-                    != 0,
-            })
-            .collect();
+        let schedules = rows.iter().map(schedule_from_row).collect();
 
         Ok(schedules)
     }
 
+    /// Schedules whose ID starts with `prefix`
+    pub async fn find_schedules_by_prefix(
+        &self,
+        prefix: &str,
+        limit: usize,
+    ) -> Result<Vec<Schedule>> {
+        let mut query = Select::from("schedules");
+        query
+            .filter_prefix("id", prefix)
+            .order_by("id")
+            .limit(Some(limit));
+
+        let rows = query.fetch_all(&self.pool).await?;
+        Ok(rows.iter().map(schedule_from_row).collect())
+    }
+
+    pub async fn get_schedule(&self, id: &str) -> Result<Option<Schedule>> {
+        let row = sqlx::query("SELECT * FROM schedules WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.as_ref().map(schedule_from_row))
+    }
+
+    pub async fn delete_schedule(&self, id: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM schedules WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Replace the editable fields of a schedule that is not running. Returns
+    /// false if it does not exist or is running.
+    pub async fn update_schedule(&self, schedule: &Schedule) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE schedules
+            SET command = ?, scheduled_time = ?, memo = ?, is_shell_mode = ?, branch = ?,
+                execution_path = ?, cron_expression = ?, claude_skip_permissions = ?,
+                claude_continue_from_last = ?, timeout_seconds = ?
+            WHERE id = ? AND status != 'running'
+            "#,
+        )
+        .bind(&schedule.command)
+        .bind(&schedule.scheduled_time)
+        .bind(&schedule._memo)
+        .bind(schedule.is_shell_mode as i32)
+        .bind(&schedule.branch)
+        .bind(&schedule.execution_path)
+        .bind(&schedule.cron_expression)
+        .bind(schedule.claude_skip_permissions as i32)
+        .bind(schedule.claude_continue_from_last as i32)
+        .bind(schedule.timeout_seconds)
+        .bind(&schedule.id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Cancel a schedule that is neither running nor already cancelled.
+    /// Returns false if nothing was cancelled.
+    pub async fn cancel_schedule(&self, id: &str) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE schedules SET status = 'cancelled'
+            WHERE id = ? AND status NOT IN ('running', 'cancelled')
+            "#,
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Move a schedule that is not running to a new time and make it pending
+    /// again, whatever it ended with. Returns false if it does not exist or is
+    /// running.
+    pub async fn reschedule_schedule(&self, id: &str, scheduled_time: &str) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE schedules SET scheduled_time = ?, status = 'pending'
+            WHERE id = ? AND status != 'running'
+            "#,
+        )
+        .bind(scheduled_time)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Set the status of a schedule. This also ends a running schedule's lease.
     pub async fn update_schedule_status(&self, id: &str, status: ScheduleStatus) -> Result<()> {
         sqlx::query(
             r#"
-            UPDATE schedules SET status = ? WHERE id = ?
+            UPDATE schedules SET status = ?, claimed_by = NULL, started_at = NULL WHERE id = ?
             "#,
         )
         .bind(status.to_db_string())
@@ -174,12 +259,96 @@ impl Database {
         Ok(())
     }
 
+    /// Mark a schedule as running under `claimed_by`. Returns false if it is
+    /// already running, has been cancelled or no longer exists.
+    pub async fn claim_schedule(&self, id: &str, claimed_by: &str) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE schedules SET status = 'running', claimed_by = ?, started_at = ?
+            WHERE id = ? AND status NOT IN ('running', 'cancelled')
+            "#,
+        )
+        .bind(claimed_by)
+        .bind(Local::now().format("%Y-%m-%d %H:%M:%S").to_string())
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Like [`Database::claim_schedule`], but only while the schedule is still
+    /// waiting for the run at `scheduled_time`. Returns false once another
+    /// process has run it or moved it on.
+    pub async fn claim_due_schedule(
+        &self,
+        id: &str,
+        claimed_by: &str,
+        scheduled_time: Option<&str>,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE schedules SET status = 'running', claimed_by = ?, started_at = ?
+            WHERE id = ? AND status IN ('pending', 'rate_limited') AND scheduled_time IS ?
+            "#,
+        )
+        .bind(claimed_by)
+        .bind(Local::now().format("%Y-%m-%d %H:%M:%S").to_string())
+        .bind(id)
+        .bind(scheduled_time)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn update_scheduled_time(&self, id: &str, scheduled_time: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE schedules SET scheduled_time = ? WHERE id = ?
+            "#,
+        )
+        .bind(scheduled_time)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn update_last_commit(&self, id: &str, commit: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE schedules SET last_commit = ? WHERE id = ?
+            "#,
+        )
+        .bind(commit)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn increment_run_count(&self, id: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE schedules SET run_count = run_count + 1 WHERE id = ?
+            "#,
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
     // Execution history methods
     pub async fn create_execution_history(&self, history: &ExecutionHistory) -> Result<()> {
         sqlx::query(
             r#"
-            INSERT INTO execution_history (id, command, executed_at, execution_type, status, output, branch, execution_path, claude_skip_permissions, claude_continue_from_last)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO execution_history (id, command, executed_at, execution_type, status, output, branch, execution_path, claude_skip_permissions, claude_continue_from_last, schedule_id, attempt, exit_code, batch_id, stdout, stderr, finished_at, duration_ms)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(&history.id)
@@ -192,6 +361,14 @@ impl Database {
         .bind(&history.execution_path)
         .bind(history.claude_skip_permissions as i32)
         .bind(history.claude_continue_from_last as i32)
+        .bind(&history.schedule_id)
+        .bind(history.attempt)
+        .bind(history.exit_code)
+        .bind(&history.batch_id)
+        .bind(&history.stdout)
+        .bind(&history.stderr)
+        .bind(&history.finished_at)
+        .bind(history.duration_ms.map(|ms| ms as i64))
         .execute(&self.pool)
         .await?;
 
@@ -200,87 +377,270 @@ impl Database {
 
     pub async fn get_execution_history(
         &self,
-        status_filter: Option<ExecutionStatus>,
-        type_filter: Option<ExecutionType>,
-        branch_filter: Option<String>,
-        from_date: Option<DateTime<Local>>,
-        to_date: Option<DateTime<Local>>,
-        limit: Option<usize>,
+        filter: &HistoryFilter,
     ) -> Result<Vec<ExecutionHistory>> {
-        let mut query = "SELECT * FROM execution_history WHERE 1=1".to_string();
-
-        if let Some(status) = status_filter {
-            query.push_str(&format!(" AND status = '{}'", status.to_db_string()));
+        let mut query = Select::from("execution_history");
+        if let Some(status) = &filter.status {
+            query.filter("status =", status.to_db_string());
         }
-
-        if let Some(exec_type) = type_filter {
-            query.push_str(&format!(
-                " AND execution_type = '{}'",
-                exec_type.to_db_string()
-            ));
+        if let Some(exec_type) = &filter.execution_type {
+            query.filter("execution_type =", exec_type.to_db_string());
         }
-
-        if let Some(branch) = branch_filter {
-            query.push_str(&format!(" AND branch = '{branch}'"));
+        if let Some(branch) = &filter.branch {
+            query.filter("branch =", branch.clone());
         }
-
-        if let Some(from) = from_date {
-            query.push_str(&format!(
-                " AND executed_at >= '{}'",
-                from.format("%Y-%m-%d %H:%M:%S")
-            ));
+        if let Some(schedule_id) = &filter.schedule_id {
+            query.filter("schedule_id =", schedule_id.clone());
         }
-
-        if let Some(to) = to_date {
-            query.push_str(&format!(
-                " AND executed_at <= '{}'",
-                to.format("%Y-%m-%d %H:%M:%S")
-            ));
+        if let Some(from) = filter.from {
+            query.filter(
+                "executed_at >=",
+                from.format("%Y-%m-%d %H:%M:%S").to_string(),
+            );
+        }
+        if let Some(to) = filter.to {
+            query.filter("executed_at <=", to.format("%Y-%m-%d %H:%M:%S").to_string());
         }
+        if let Some(min_duration) = filter.min_duration {
+            let min_duration_ms = i64::try_from(min_duration.as_millis()).unwrap_or(i64::MAX);
+            query.filter("duration_ms >=", min_duration_ms);
+        }
+        if let Some(exit_code) = filter.exit_code {
+            query.filter("exit_code =", exit_code);
+        }
+        query.order_by("executed_at DESC").limit(filter.limit);
 
-        query.push_str(" ORDER BY executed_at DESC");
+        let rows = query.fetch_all(&self.pool).await?;
 
-        if let Some(limit) = limit {
-            query.push_str(&format!(" LIMIT {limit}"));
-        }
+        let history = rows.iter().map(history_from_row).collect();
 
-        let rows = sqlx::query(&query).fetch_all(&self.pool).await?;
-
-        let history = rows
-            .into_iter()
-            .map(|row| ExecutionHistory {
-                id: sqlx::Row::get(&row, "id"),
-                command: sqlx::Row::get(&row, "command"),
-                executed_at: sqlx::Row::get(&row, "executed_at"),
-                execution_type: ExecutionType::from_string(&sqlx::Row::get::<String, _>(
-                    &row,
-                    "execution_type",
-                )),
-                status: ExecutionStatus::from_string(&sqlx::Row::get::<String, _>(&row, "status")),
-                output: sqlx::Row::get(&row, "output"),
-                branch: sqlx::Row::get(&row, "branch"),
-                execution_path: sqlx::Row::try_get(&row, "execution_path").unwrap_or_else(|_| {
-                    std::env::current_dir()
-                        .unwrap_or_else(|_| std::path::PathBuf::from("."))
-                        .to_string_lossy()
-                        .to_string()
-                }),
-                claude_skip_permissions: sqlx::Row::try_get::<i32, _>(
-                    &row,
-                    "claude_skip_permissions",
-                )
-                .unwrap_or(0)
-                    != 0,
-                claude_continue_from_last: sqlx::Row::try_get::<i32, _>(
-                    &row,
-                    "claude_continue_from_last",
-                )
-                .unwrap_or(0)
-                    != 0,
+        Ok(history)
+    }
+
+    /// Full-text search over the command and output of the execution history,
+    /// best matches first. Every whitespace separated term must appear; the
+    /// matched text in the snippet is wrapped in `highlight`.
+    pub async fn search_execution_history(
+        &self,
+        query: &str,
+        limit: usize,
+        highlight: (&str, &str),
+    ) -> Result<Vec<SearchHit>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT execution_history.*,
+                snippet(execution_history_fts, -1, ?, ?, '…', 16) AS snippet
+            FROM execution_history_fts
+            JOIN execution_history ON execution_history.id = execution_history_fts.id
+            WHERE execution_history_fts MATCH ?
+            ORDER BY rank
+            LIMIT ?
+            "#,
+        )
+        .bind(highlight.0)
+        .bind(highlight.1)
+        .bind(fts_query(query)?)
+        .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+        .fetch_all(&self.pool)
+        .await?;
+
+        let hits = rows
+            .iter()
+            .map(|row| SearchHit {
+                history: history_from_row(row),
+                snippet: sqlx::Row::get(row, "snippet"),
             })
             .collect();
 
-        Ok(history)
+        Ok(hits)
+    }
+
+    /// Delete the whole execution history. Returns how many entries were deleted.
+    #[cfg(feature = "gui")]
+    pub async fn clear_execution_history(&self) -> Result<u64> {
+        let mut transaction = self.pool.begin().await?;
+        // Emptying the search index first saves the delete trigger from
+        // looking up every entry in it
+        sqlx::query("DELETE FROM execution_history_fts")
+            .execute(&mut *transaction)
+            .await?;
+        let result = sqlx::query("DELETE FROM execution_history")
+            .execute(&mut *transaction)
+            .await?;
+        transaction.commit().await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Executions whose ID starts with `prefix`
+    pub async fn find_executions_by_prefix(
+        &self,
+        prefix: &str,
+        limit: usize,
+    ) -> Result<Vec<ExecutionHistory>> {
+        let mut query = Select::from("execution_history");
+        query
+            .filter_prefix("id", prefix)
+            .order_by("id")
+            .limit(Some(limit));
+
+        let rows = query.fetch_all(&self.pool).await?;
+        Ok(rows.iter().map(history_from_row).collect())
+    }
+
+    pub async fn get_execution(&self, id: &str) -> Result<Option<ExecutionHistory>> {
+        let row = sqlx::query("SELECT * FROM execution_history WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.as_ref().map(history_from_row))
+    }
+
+    // Running execution methods
+    pub async fn start_execution_process(&self, process: &ExecutionProcess) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO running_executions
+            (execution_id, schedule_id, command, pid, daemon_id, started_at, cancel_requested)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&process.execution_id)
+        .bind(&process.schedule_id)
+        .bind(&process.command)
+        .bind(process.pid)
+        .bind(&process.daemon_id)
+        .bind(&process.started_at)
+        .bind(process.cancel_requested as i32)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn set_execution_pid(&self, execution_id: &str, pid: u32) -> Result<()> {
+        sqlx::query("UPDATE running_executions SET pid = ? WHERE execution_id = ?")
+            .bind(pid)
+            .bind(execution_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn finish_execution_process(&self, execution_id: &str) -> Result<()> {
+        sqlx::query("DELETE FROM running_executions WHERE execution_id = ?")
+            .bind(execution_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_execution_process(
+        &self,
+        execution_id: &str,
+    ) -> Result<Option<ExecutionProcess>> {
+        let row = sqlx::query("SELECT * FROM running_executions WHERE execution_id = ?")
+            .bind(execution_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.as_ref().map(process_from_row))
+    }
+
+    /// Ask the daemon running an execution to stop it. Returns false if it is
+    /// not running.
+    pub async fn request_execution_cancel(&self, execution_id: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE running_executions SET cancel_requested = 1 WHERE execution_id = ?",
+        )
+        .bind(execution_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Executions of `daemon_id` that someone asked to stop
+    pub async fn get_cancel_requests(&self, daemon_id: &str) -> Result<Vec<String>> {
+        let rows = sqlx::query_as::<_, (String,)>(
+            "SELECT execution_id FROM running_executions WHERE daemon_id = ? AND cancel_requested != 0",
+        )
+        .bind(daemon_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|(id,)| id).collect())
+    }
+
+    pub async fn get_execution_processes(&self) -> Result<Vec<ExecutionProcess>> {
+        let rows = sqlx::query("SELECT * FROM running_executions ORDER BY started_at")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(process_from_row).collect())
+    }
+
+    /// Record lines a running command has printed
+    pub async fn append_output_lines(
+        &self,
+        execution_id: &str,
+        lines: &[OutputLine],
+    ) -> Result<()> {
+        let mut transaction = self.pool.begin().await?;
+        for line in lines {
+            sqlx::query(
+                "INSERT INTO execution_output (execution_id, line, stream, text, written_at) VALUES (?, ?, ?, ?, ?)",
+            )
+            .bind(execution_id)
+            .bind(line.line as i64)
+            .bind(&line.stream)
+            .bind(&line.text)
+            .bind(&line.written_at)
+            .execute(&mut *transaction)
+            .await?;
+        }
+        transaction.commit().await?;
+
+        Ok(())
+    }
+
+    /// Recorded output lines of an execution, oldest first
+    pub async fn get_output_lines(
+        &self,
+        execution_id: &str,
+        filter: &OutputFilter,
+    ) -> Result<Vec<OutputLine>> {
+        let mut query = Select::from("execution_output");
+        query
+            .filter("execution_id =", execution_id.to_string())
+            .filter("line >", filter.after_line as i64);
+        if let Some(stream) = filter.stream {
+            query.filter("stream =", stream.as_str());
+        }
+        if let Some(since) = &filter.since {
+            query.filter("written_at >=", since.clone());
+        }
+        if let Some(text) = &filter.contains {
+            query.filter_contains("text", text);
+        }
+        query.order_by("line");
+
+        let rows = query.fetch_all(&self.pool).await?;
+        Ok(rows.iter().map(output_line_from_row).collect())
+    }
+
+    /// Whether any output lines were recorded for an execution. Only runs by
+    /// a daemon record them.
+    pub async fn has_output_lines(&self, execution_id: &str) -> Result<bool> {
+        let row = sqlx::query("SELECT 1 FROM execution_output WHERE execution_id = ? LIMIT 1")
+            .bind(execution_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.is_some())
     }
 
     // Configuration methods
@@ -319,3 +679,196 @@ impl Database {
         Ok(rows)
     }
 }
+
+/// Turn search terms into an FTS5 query that matches entries containing all of
+/// them. Each term is quoted, so characters such as `-` or `*` are searched
+/// for literally instead of being parsed as FTS5 syntax.
+fn fts_query(query: &str) -> Result<String> {
+    let terms: Vec<&str> = query.split_whitespace().collect();
+    if terms.is_empty() {
+        bail!("The search query is empty");
+    }
+    // The trigram index cannot find anything shorter than 3 characters
+    if let Some(term) = terms.iter().find(|term| term.chars().count() < 3) {
+        bail!("Search terms must be at least 3 characters long: {term}");
+    }
+
+    Ok(terms
+        .iter()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" "))
+}
+
+/// A `SELECT` whose filters are always bound as parameters, so that values from
+/// the CLI or the HTTP API never become part of the SQL text. Only the column
+/// names and operators, which are written in this file, are.
+struct Select {
+    builder: QueryBuilder<'static, Sqlite>,
+    has_filter: bool,
+}
+
+impl Select {
+    fn from(table: &'static str) -> Self {
+        Self {
+            builder: QueryBuilder::new(format!("SELECT * FROM {table}")),
+            has_filter: false,
+        }
+    }
+
+    /// Add `AND <condition> ?`, e.g. `filter("branch =", branch)`
+    fn filter<T>(&mut self, condition: &'static str, value: T) -> &mut Self
+    where
+        T: 'static + Encode<'static, Sqlite> + Type<Sqlite> + Send,
+    {
+        self.start_condition();
+        self.builder.push(condition).push(" ").push_bind(value);
+        self
+    }
+
+    /// Add `AND <column> LIKE '<prefix>%'`, matching `%` and `_` in the
+    /// prefix literally
+    fn filter_prefix(&mut self, column: &'static str, prefix: &str) -> &mut Self {
+        let pattern = format!(
+            "{}%",
+            prefix
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        self.start_condition();
+        self.builder
+            .push(column)
+            .push(" LIKE ")
+            .push_bind(pattern)
+            .push(" ESCAPE '\\'");
+        self
+    }
+
+    /// Add `AND instr(<column>, ?) > 0`, a case-sensitive substring match
+    fn filter_contains(&mut self, column: &'static str, text: &str) -> &mut Self {
+        self.start_condition();
+        self.builder
+            .push("instr(")
+            .push(column)
+            .push(", ")
+            .push_bind(text.to_string())
+            .push(") > 0");
+        self
+    }
+
+    fn start_condition(&mut self) {
+        self.builder
+            .push(if self.has_filter { " AND " } else { " WHERE " });
+        self.has_filter = true;
+    }
+
+    fn order_by(&mut self, order: &'static str) -> &mut Self {
+        self.builder.push(" ORDER BY ").push(order);
+        self
+    }
+
+    fn limit(&mut self, limit: Option<usize>) -> &mut Self {
+        if let Some(limit) = limit {
+            self.builder
+                .push(" LIMIT ")
+                .push_bind(i64::try_from(limit).unwrap_or(i64::MAX));
+        }
+        self
+    }
+
+    async fn fetch_all(&mut self, pool: &SqlitePool) -> Result<Vec<SqliteRow>> {
+        Ok(self.builder.build().fetch_all(pool).await?)
+    }
+}
+
+fn schedule_from_row(row: &SqliteRow) -> Schedule {
+    Schedule {
+        id: sqlx::Row::get(row, "id"),
+        command: sqlx::Row::get(row, "command"),
+        scheduled_time: sqlx::Row::get(row, "scheduled_time"),
+        _memo: sqlx::Row::get(row, "memo"),
+        created_at: sqlx::Row::get(row, "created_at"),
+        status: ScheduleStatus::from_string(&sqlx::Row::get::<String, _>(row, "status")),
+        is_shell_mode: sqlx::Row::get::<i32, _>(row, "is_shell_mode") != 0,
+        branch: sqlx::Row::get(row, "branch"),
+        execution_path: sqlx::Row::get(row, "execution_path"),
+        claude_skip_permissions: sqlx::Row::get::<i32, _>(row, "claude_skip_permissions") != 0,
+        claude_continue_from_last: sqlx::Row::get::<i32, _>(row, "claude_continue_from_last") != 0,
+        cron_expression: sqlx::Row::get(row, "cron_expression"),
+        interval_minutes: sqlx::Row::get(row, "interval_minutes"),
+        max_runs: sqlx::Row::get(row, "max_runs"),
+        repeat_until: sqlx::Row::get(row, "repeat_until"),
+        run_count: sqlx::Row::get(row, "run_count"),
+        timeout_seconds: sqlx::Row::get(row, "timeout_seconds"),
+        misfire_policy: MisfirePolicy::from_string(&sqlx::Row::get::<String, _>(
+            row,
+            "misfire_policy",
+        )),
+        misfire_grace_seconds: sqlx::Row::get(row, "misfire_grace_seconds"),
+        retry_max_attempts: sqlx::Row::get(row, "retry_max_attempts"),
+        retry_delay_seconds: sqlx::Row::get(row, "retry_delay_seconds"),
+        retry_multiplier: sqlx::Row::get(row, "retry_multiplier"),
+        retry_on_exit_codes: serde_json::from_str(sqlx::Row::get(row, "retry_on_exit_codes"))
+            .unwrap_or_default(),
+        retry_on_output: serde_json::from_str(sqlx::Row::get(row, "retry_on_output"))
+            .unwrap_or_default(),
+        continue_after_limit: sqlx::Row::get::<i32, _>(row, "continue_after_limit") != 0,
+        depends_on: serde_json::from_str(sqlx::Row::get(row, "depends_on")).unwrap_or_default(),
+        on_dependency_failure: DependencyFailurePolicy::from_string(&sqlx::Row::get::<String, _>(
+            row,
+            "on_dependency_failure",
+        )),
+        conditions: serde_json::from_str(sqlx::Row::get(row, "conditions")).unwrap_or_default(),
+        last_commit: sqlx::Row::get(row, "last_commit"),
+        claimed_by: sqlx::Row::get(row, "claimed_by"),
+        started_at: sqlx::Row::get(row, "started_at"),
+    }
+}
+
+fn history_from_row(row: &SqliteRow) -> ExecutionHistory {
+    ExecutionHistory {
+        id: sqlx::Row::get(row, "id"),
+        command: sqlx::Row::get(row, "command"),
+        executed_at: sqlx::Row::get(row, "executed_at"),
+        execution_type: ExecutionType::from_string(&sqlx::Row::get::<String, _>(
+            row,
+            "execution_type",
+        )),
+        status: ExecutionStatus::from_string(&sqlx::Row::get::<String, _>(row, "status")),
+        output: sqlx::Row::get(row, "output"),
+        branch: sqlx::Row::get(row, "branch"),
+        execution_path: sqlx::Row::get(row, "execution_path"),
+        claude_skip_permissions: sqlx::Row::get::<i32, _>(row, "claude_skip_permissions") != 0,
+        claude_continue_from_last: sqlx::Row::get::<i32, _>(row, "claude_continue_from_last") != 0,
+        schedule_id: sqlx::Row::get(row, "schedule_id"),
+        attempt: sqlx::Row::get(row, "attempt"),
+        exit_code: sqlx::Row::get(row, "exit_code"),
+        batch_id: sqlx::Row::get(row, "batch_id"),
+        stdout: sqlx::Row::get(row, "stdout"),
+        stderr: sqlx::Row::get(row, "stderr"),
+        finished_at: sqlx::Row::get(row, "finished_at"),
+        duration_ms: sqlx::Row::get::<Option<i64>, _>(row, "duration_ms").map(|ms| ms as u64),
+    }
+}
+
+fn output_line_from_row(row: &SqliteRow) -> OutputLine {
+    OutputLine {
+        line: sqlx::Row::get::<i64, _>(row, "line") as u64,
+        stream: sqlx::Row::get(row, "stream"),
+        text: sqlx::Row::get(row, "text"),
+        written_at: sqlx::Row::get(row, "written_at"),
+    }
+}
+
+fn process_from_row(row: &SqliteRow) -> ExecutionProcess {
+    ExecutionProcess {
+        execution_id: sqlx::Row::get(row, "execution_id"),
+        schedule_id: sqlx::Row::get(row, "schedule_id"),
+        command: sqlx::Row::get(row, "command"),
+        pid: sqlx::Row::get(row, "pid"),
+        daemon_id: sqlx::Row::get(row, "daemon_id"),
+        started_at: sqlx::Row::get(row, "started_at"),
+        cancel_requested: sqlx::Row::get::<i32, _>(row, "cancel_requested") != 0,
+    }
+}
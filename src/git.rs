@@ -142,6 +142,24 @@ pub fn get_current_branch_in_directory(directory: &str) -> String {
     }
 }
 
+/// 指定されたディレクトリのHEADのコミットハッシュを取得
+///
+/// gitリポジトリでない場合やコミットがない場合はNoneを返す。
+pub fn get_head_commit_in_directory(directory: &str) -> Option<String> {
+    let expanded_path = crate::utils::expand_path(directory);
+
+    let output = Command::new("git")
+        .current_dir(&expanded_path)
+        .args(["rev-parse", "--verify", "--quiet", "HEAD"])
+        .output()
+        .ok()?;
+
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
 /// 現在のbranchを取得（後方互換性のため）
 #[allow(dead_code)]
 pub fn get_current_branch() -> String {
@@ -170,16 +188,64 @@ pub fn get_worktree_path(branch: &str) -> Result<String, std::io::Error> {
         .to_string_lossy()
         .to_string();
 
-    let expanded_path = crate::utils::expand_path(&current_dir);
-    let worktree_path = format!("{expanded_path}/claude-schedular-{branch}");
+    Ok(get_worktree_path_in_directory(&current_dir, branch))
+}
+
+/// 指定されたディレクトリ配下にあるbranchのworktreeパスを取得
+///
+/// 以前の名前（`claude-schedular-{branch}`）のworktreeも探す。
+/// worktreeが存在しない場合は指定されたディレクトリを返す。
+pub fn get_worktree_path_in_directory(directory: &str, branch: &str) -> String {
+    let expanded_path = crate::utils::expand_path(directory);
 
     // worktreeが存在するか確認
-    if std::path::Path::new(&worktree_path).exists() {
-        Ok(worktree_path)
+    [
+        format!("{expanded_path}/claude-scheduler-{branch}"),
+        format!("{expanded_path}/claude-schedular-{branch}"),
+    ]
+    .into_iter()
+    .find(|worktree_path| std::path::Path::new(worktree_path).exists())
+    .unwrap_or_else(|| directory.to_string())
+}
+
+/// 指定されたディレクトリ配下にbranchのworktreeを作成してパスを返す
+///
+/// worktreeが既にある場合はそのまま使い、branchが存在しない場合は現在のHEADから作成する。
+pub fn create_worktree(directory: &str, branch: &str) -> anyhow::Result<String> {
+    let existing = get_worktree_path_in_directory(directory, branch);
+    if existing != directory {
+        return Ok(existing);
+    }
+
+    let expanded_path = crate::utils::expand_path(directory);
+    let worktree_path = format!("{expanded_path}/claude-scheduler-{branch}");
+
+    let branch_exists = Command::new("git")
+        .current_dir(&expanded_path)
+        .args(["rev-parse", "--verify", "--quiet"])
+        .arg(format!("refs/heads/{branch}"))
+        .output()
+        .is_ok_and(|output| output.status.success());
+
+    let mut command = Command::new("git");
+    command
+        .current_dir(&expanded_path)
+        .args(["worktree", "add"]);
+    if branch_exists {
+        command.arg(&worktree_path).arg(branch);
     } else {
-        // worktreeが存在しない場合は現在のディレクトリを返す
-        Ok(current_dir)
+        command.arg("-b").arg(branch).arg(&worktree_path);
     }
+
+    let output = command.output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Failed to create a worktree for '{branch}': {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(worktree_path)
 }
 
 /// git worktreeでのコマンド実行
@@ -1,14 +1,22 @@
+pub mod api;
+pub mod batch;
 pub mod cli;
 pub mod cli_commands;
 pub mod cli_handlers;
 #[cfg(feature = "gui")]
 pub mod components;
 pub mod config;
+pub mod cron;
+pub mod daemon;
+pub mod daemonize;
 pub mod database;
 pub mod git;
+pub mod live_output;
+pub mod logging;
+pub mod migrations;
 pub mod models;
-#[cfg(feature = "gui")]
 pub mod persistence;
+pub mod usage_limit;
 pub mod utils;
 
 #[cfg(test)]
@@ -27,8 +35,13 @@ mod tests {
         use models::ScheduleStatus;
 
         assert_eq!(ScheduleStatus::Pending.to_string(), "待機中");
+        assert_eq!(ScheduleStatus::Running.to_string(), "実行中");
         assert_eq!(ScheduleStatus::Completed.to_string(), "完了");
         assert_eq!(ScheduleStatus::Failed.to_string(), "失敗");
+        assert_eq!(
+            ScheduleStatus::from_string("canceled"),
+            ScheduleStatus::Cancelled
+        );
     }
 
     #[test]
@@ -37,6 +50,99 @@ mod tests {
 
         assert_eq!(ExecutionStatus::Success.to_string(), "成功");
         assert_eq!(ExecutionStatus::Failed.to_string(), "失敗");
+        assert_eq!(ExecutionStatus::Skipped.to_string(), "スキップ");
+        assert_eq!(
+            ExecutionStatus::from_string(&ExecutionStatus::Cancelled.to_db_string()),
+            ExecutionStatus::Cancelled
+        );
+    }
+
+    #[test]
+    fn test_models_misfire_policy() {
+        use models::{MisfirePolicy, Schedule};
+
+        assert_eq!(MisfirePolicy::from_string("run-all"), MisfirePolicy::RunAll);
+        assert_eq!(MisfirePolicy::from_string("skip"), MisfirePolicy::Skip);
+        assert_eq!(
+            MisfirePolicy::from_string("unknown"),
+            MisfirePolicy::RunOnce
+        );
+        assert_eq!(MisfirePolicy::RunAll.to_db_string(), "run_all");
+
+        let mut schedule = Schedule::default();
+        assert_eq!(schedule.misfire_grace(), chrono::Duration::minutes(5));
+        schedule.misfire_grace_seconds = Some(30);
+        assert_eq!(schedule.misfire_grace(), chrono::Duration::seconds(30));
+    }
+
+    #[test]
+    fn test_models_schedule_condition() {
+        use models::ScheduleCondition;
+
+        let conditions = vec![
+            ScheduleCondition::NewCommits,
+            ScheduleCondition::FileExists {
+                path: "target/report.json".to_string(),
+            },
+            ScheduleCondition::Command {
+                command: "test -n \"$(git status --porcelain)\"".to_string(),
+            },
+        ];
+        let json = serde_json::to_string(&conditions).unwrap();
+        assert!(json.starts_with(r#"[{"type":"new_commits"},{"type":"file_exists","path":"#));
+        assert_eq!(
+            serde_json::from_str::<Vec<ScheduleCondition>>(&json).unwrap(),
+            conditions
+        );
+        assert_eq!(conditions[1].to_string(), "file exists: target/report.json");
+    }
+
+    #[test]
+    fn test_models_retry_policy() {
+        use models::{ExecutionHistory, ExecutionStatus, ExecutionType, Schedule};
+        use std::time::Duration;
+
+        let mut history = ExecutionHistory {
+            id: "exec_1".to_string(),
+            command: "claude".to_string(),
+            executed_at: "2024-01-01 10:00:00".to_string(),
+            execution_type: ExecutionType::FromSchedule,
+            status: ExecutionStatus::Failed,
+            output: "API Error: overloaded".to_string(),
+            branch: "main".to_string(),
+            execution_path: ".".to_string(),
+            claude_skip_permissions: false,
+            claude_continue_from_last: false,
+            schedule_id: None,
+            attempt: Some(1),
+            exit_code: Some(1),
+            batch_id: None,
+            stdout: String::new(),
+            stderr: "API Error: overloaded".to_string(),
+            finished_at: None,
+            duration_ms: None,
+        };
+
+        let mut schedule = Schedule::default();
+        assert!(!schedule.should_retry(&history, 1));
+
+        schedule.retry_max_attempts = Some(3);
+        assert!(schedule.should_retry(&history, 1));
+        assert!(schedule.should_retry(&history, 2));
+        assert!(!schedule.should_retry(&history, 3));
+
+        schedule.retry_on_exit_codes = vec![75];
+        assert!(!schedule.should_retry(&history, 1));
+        schedule.retry_on_output = vec!["overloaded".to_string()];
+        assert!(schedule.should_retry(&history, 1));
+
+        history.status = ExecutionStatus::Interrupted;
+        assert!(!schedule.should_retry(&history, 1));
+
+        schedule.retry_delay_seconds = Some(10);
+        schedule.retry_multiplier = Some(3.0);
+        assert_eq!(schedule.retry_delay(1), Duration::from_secs(10));
+        assert_eq!(schedule.retry_delay(3), Duration::from_secs(90));
     }
 
     #[test]
@@ -68,6 +174,885 @@ mod tests {
         assert!(!utils::is_time_reached(&future_time));
     }
 
+    #[test]
+    fn test_cron_next_after() {
+        use chrono::NaiveDateTime;
+        use cron::CronExpression;
+
+        let parse = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M").unwrap();
+
+        // 平日の3時: 金曜日の実行後は月曜日
+        let weekdays: CronExpression = "0 3 * * 1-5".parse().unwrap();
+        assert_eq!(
+            weekdays.next_after(parse("2025-01-03T03:00")),
+            Some(parse("2025-01-06T03:00"))
+        );
+
+        // 6フィールド形式とショートカット
+        let every_15: CronExpression = "0 */15 * * * *".parse().unwrap();
+        assert_eq!(
+            every_15.next_after(parse("2025-01-01T10:07")),
+            Some(parse("2025-01-01T10:15"))
+        );
+        let daily: CronExpression = "@daily".parse().unwrap();
+        assert_eq!(
+            daily.next_after(parse("2025-12-31T12:00")),
+            Some(parse("2026-01-01T00:00"))
+        );
+
+        // 日と曜日の両方が指定された場合はどちらか一方に一致すれば良い
+        let either: CronExpression = "30 9 13 * fri".parse().unwrap();
+        assert_eq!(
+            either.next_after(parse("2025-01-01T00:00")),
+            Some(parse("2025-01-03T09:30"))
+        );
+    }
+
+    #[test]
+    fn test_cron_invalid_expressions() {
+        use cron::CronExpression;
+
+        assert!("* * * *".parse::<CronExpression>().is_err());
+        assert!("60 * * * *".parse::<CronExpression>().is_err());
+        assert!("0 0 * * mon-foo".parse::<CronExpression>().is_err());
+        assert!("30 * * * * *".parse::<CronExpression>().is_err());
+        assert!("@reboot".parse::<CronExpression>().is_err());
+
+        let never: CronExpression = "0 0 31 2 *".parse().unwrap();
+        assert!(never
+            .next_after(chrono::Local::now().naive_local())
+            .is_none());
+    }
+
+    #[test]
+    fn test_utils_parse_duration() {
+        use chrono::Duration;
+
+        assert_eq!(utils::parse_duration("45m").unwrap(), Duration::minutes(45));
+        assert_eq!(
+            utils::parse_duration("1h30m").unwrap(),
+            Duration::minutes(90)
+        );
+        assert_eq!(utils::parse_duration("2d").unwrap(), Duration::days(2));
+        assert_eq!(utils::parse_duration("15").unwrap(), Duration::minutes(15));
+        assert!(utils::parse_duration("10x").is_err());
+        assert!(utils::parse_duration("").is_err());
+        assert!(utils::parse_duration("99999999999999d").is_err());
+        assert!(utils::parse_duration("99999999999999999999").is_err());
+
+        assert_eq!(utils::format_duration(Duration::minutes(90)), "1h30m");
+        assert_eq!(utils::format_duration(Duration::seconds(5)), "5s");
+    }
+
+    #[test]
+    fn test_models_interval_schedule() {
+        use chrono::NaiveDateTime;
+        use models::Schedule;
+
+        let parse = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M").unwrap();
+
+        let mut schedule = Schedule {
+            scheduled_time: Some("2025-01-01T10:00".to_string()),
+            interval_minutes: Some(120),
+            repeat_until: Some("2025-01-01T18:00".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            schedule.next_occurrence(parse("2025-01-01T10:01")),
+            Some("2025-01-01T12:00".to_string())
+        );
+
+        // 停止中に過ぎた回は飛ばして間隔を維持する
+        assert_eq!(
+            schedule.next_occurrence(parse("2025-01-01T15:30")),
+            Some("2025-01-01T16:00".to_string())
+        );
+
+        // 終了日時ちょうどは実行し、それを過ぎる場合は繰り返さない
+        assert_eq!(
+            schedule.next_occurrence(parse("2025-01-01T16:30")),
+            Some("2025-01-01T18:00".to_string())
+        );
+        assert_eq!(schedule.next_occurrence(parse("2025-01-01T18:00")), None);
+
+        // 実行回数の上限
+        schedule.repeat_until = None;
+        schedule.max_runs = Some(3);
+        schedule.run_count = 3;
+        assert_eq!(schedule.remaining_runs(), Some(0));
+        assert_eq!(schedule.next_occurrence(parse("2025-01-01T10:01")), None);
+    }
+
+    #[test]
+    fn test_usage_limit_reset_time() {
+        use chrono::NaiveDate;
+
+        let now = NaiveDate::from_ymd_opt(2024, 1, 1)
+            .unwrap()
+            .and_hms_opt(10, 0, 0)
+            .unwrap();
+        let at = |h, m| {
+            NaiveDate::from_ymd_opt(2024, 1, 1)
+                .unwrap()
+                .and_hms_opt(h, m, 0)
+                .unwrap()
+        };
+
+        assert_eq!(
+            usage_limit::detect_reset_time(
+                "Claude usage limit reached. Your limit will reset at 3pm (Asia/Tokyo).",
+                now
+            ),
+            Some(at(15, 0))
+        );
+        assert_eq!(
+            usage_limit::detect_reset_time("5-hour limit reached ∙ resets 10:30am", now),
+            Some(at(10, 30))
+        );
+        // A reset time that has already passed today is tomorrow
+        assert_eq!(
+            usage_limit::detect_reset_time("usage limit reached, resets at 9:00", now),
+            Some(at(9, 0) + chrono::Duration::days(1))
+        );
+        assert_eq!(
+            usage_limit::detect_reset_time("Claude AI usage limit reached", now),
+            Some(at(11, 0))
+        );
+        assert_eq!(
+            usage_limit::detect_reset_time("All tests passed", now),
+            None
+        );
+
+        // Only stderr and the end of stdout are looked at
+        let quoted = "The log said \"usage limit reached\".\n1\n2\n3\n4\n5";
+        assert_eq!(usage_limit::detect_in_failed_run(quoted, "", now), None);
+        assert_eq!(
+            usage_limit::detect_in_failed_run("", "Claude AI usage limit reached", now),
+            Some(at(11, 0))
+        );
+        assert_eq!(
+            usage_limit::detect_in_failed_run("done\nClaude AI usage limit reached", "", now),
+            Some(at(11, 0))
+        );
+    }
+
+    #[test]
+    fn test_logging_rotating_file() {
+        use std::io::Write;
+
+        let dir = std::env::temp_dir().join(format!("claude-scheduler-log-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("daemon.log");
+
+        let mut log =
+            logging::RotatingFile::open(&path, Some(10), logging::Rotation::Never, 2).unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            log.write_all(line.as_bytes()).unwrap();
+        }
+
+        // 10バイトを超える度にローテーションし、古いものは2つまで残る
+        let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+        assert_eq!(read("daemon.log"), "fourth\n");
+        assert_eq!(read("daemon.log.1"), "third\n");
+        assert_eq!(read("daemon.log.2"), "second\n");
+        assert!(!dir.join("daemon.log.3").exists());
+
+        assert_eq!(utils::parse_size("10MB").unwrap(), 10 * 1024 * 1024);
+        assert_eq!(utils::parse_size("512kb").unwrap(), 512 * 1024);
+        assert!(utils::parse_size("10XB").is_err());
+        assert!(utils::parse_size("18446744073709551615GB").is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_database_migrations() {
+        let dir = std::env::temp_dir().join(format!("claude-scheduler-db-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let path = dir.join("db.sqlite");
+
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            // A database from before versioned migrations lacks later columns
+            database::Database::connect(&path).await.unwrap();
+            let pool = sqlx::SqlitePool::connect(&format!("sqlite:{}", path.display()))
+                .await
+                .unwrap();
+            assert_eq!(migrations::current_version(&pool).await.unwrap(), 0);
+            sqlx::raw_sql(
+                "CREATE TABLE schedules (id TEXT PRIMARY KEY, command TEXT NOT NULL, scheduled_time TEXT, memo TEXT, created_at TEXT NOT NULL, status TEXT NOT NULL, is_shell_mode INTEGER NOT NULL, branch TEXT NOT NULL);
+                 INSERT INTO schedules VALUES ('schedule_1', 'echo', NULL, '', '2025-01-01 00:00:00', 'pending', 1, 'main');",
+            )
+            .execute(&pool)
+            .await
+            .unwrap();
+
+            let db = database::Database::new(&path).await.unwrap();
+            let schedule = db.get_schedule("schedule_1").await.unwrap().unwrap();
+            assert_eq!(schedule.execution_path, ".");
+            assert_eq!(schedule.misfire_policy, models::MisfirePolicy::RunOnce);
+            assert!(db.migrate().await.unwrap().is_empty());
+
+            // A database migrated by a newer version is refused
+            sqlx::query("INSERT INTO schema_version VALUES (?, 'future', '')")
+                .bind(migrations::latest_version() + 1)
+                .execute(&pool)
+                .await
+                .unwrap();
+            assert!(database::Database::new(&path).await.is_err());
+        });
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// An execution history entry for database tests, with nothing recorded
+    /// but its id and start time
+    fn history_entry(id: &str, executed_at: &str) -> models::ExecutionHistory {
+        models::ExecutionHistory {
+            id: id.to_string(),
+            command: "echo".to_string(),
+            executed_at: executed_at.to_string(),
+            execution_type: models::ExecutionType::Manual,
+            status: models::ExecutionStatus::Success,
+            output: String::new(),
+            branch: "main".to_string(),
+            execution_path: ".".to_string(),
+            claude_skip_permissions: false,
+            claude_continue_from_last: false,
+            schedule_id: None,
+            attempt: None,
+            exit_code: None,
+            batch_id: None,
+            stdout: String::new(),
+            stderr: String::new(),
+            finished_at: None,
+            duration_ms: None,
+        }
+    }
+
+    #[test]
+    fn test_database_hostile_filters() {
+        use chrono::{Local, NaiveDate, TimeZone};
+        use models::{ExecutionHistory, Schedule};
+
+        let dir =
+            std::env::temp_dir().join(format!("claude-scheduler-filters-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let db = database::Database::new(&dir.join("db.sqlite"))
+                .await
+                .unwrap();
+            let hostile = [
+                "feature/it's",
+                "' OR '1'='1",
+                "x'; DROP TABLE execution_history; --",
+                "\" OR 1=1 --",
+            ];
+            for (i, branch) in hostile.iter().enumerate() {
+                db.create_execution_history(&ExecutionHistory {
+                    branch: branch.to_string(),
+                    ..history_entry(
+                        &format!("exec_{i}"),
+                        &format!("2025-01-0{} 12:00:00", i + 1),
+                    )
+                })
+                .await
+                .unwrap();
+            }
+
+            // Every branch name matches exactly its own entry
+            for (i, branch) in hostile.iter().enumerate() {
+                let history = db
+                    .get_execution_history(&database::HistoryFilter {
+                        branch: Some(branch.to_string()),
+                        ..Default::default()
+                    })
+                    .await
+                    .unwrap();
+                assert_eq!(history.len(), 1);
+                assert_eq!(history[0].id, format!("exec_{i}"));
+            }
+
+            // Dates are compared as values; a date range selects only its own days
+            let day = |d| {
+                Local
+                    .from_local_datetime(
+                        &NaiveDate::from_ymd_opt(2025, 1, d)
+                            .unwrap()
+                            .and_hms_opt(0, 0, 0)
+                            .unwrap(),
+                    )
+                    .unwrap()
+            };
+            let history = db
+                .get_execution_history(&database::HistoryFilter {
+                    from: Some(day(2)),
+                    to: Some(day(4)),
+                    limit: Some(5),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            let ids: Vec<_> = history.iter().map(|h| h.id.as_str()).collect();
+            assert_eq!(ids, ["exec_2", "exec_1"]);
+
+            // Hostile values stored in a schedule round-trip unchanged
+            let schedule = Schedule {
+                id: "schedule_'; DELETE FROM schedules; --".to_string(),
+                scheduled_time: Some("2025-01-01 00:00:00' OR '1'='1".to_string()),
+                branch: hostile[1].to_string(),
+                ..Schedule::default()
+            };
+            db.create_schedule(&schedule).await.unwrap();
+            let stored = db.get_schedule(&schedule.id).await.unwrap().unwrap();
+            assert_eq!(stored.scheduled_time, schedule.scheduled_time);
+            assert_eq!(stored.branch, hostile[1]);
+            assert_eq!(db.get_schedules(None, Some(10)).await.unwrap().len(), 1);
+            assert_eq!(
+                db.get_execution_history(&database::HistoryFilter::default())
+                    .await
+                    .unwrap()
+                    .len(),
+                hostile.len()
+            );
+        });
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_database_search() {
+        use models::ExecutionHistory;
+
+        let dir =
+            std::env::temp_dir().join(format!("claude-scheduler-search-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let db = database::Database::new(&dir.join("db.sqlite"))
+                .await
+                .unwrap();
+            for (i, output) in [
+                "checked out ' OR '1'='1",
+                "checked out x'; DROP TABLE execution_history; --",
+                "checked out \" OR 1=1 --",
+            ]
+            .iter()
+            .enumerate()
+            {
+                db.create_execution_history(&ExecutionHistory {
+                    output: output.to_string(),
+                    ..history_entry(&format!("exec_{i}"), "2025-01-01 12:00:00")
+                })
+                .await
+                .unwrap();
+            }
+
+            // Search terms are matched literally, not parsed as FTS5 syntax
+            let hits = db
+                .search_execution_history("DROP \"1=1", 10, ("[", "]"))
+                .await
+                .unwrap();
+            assert!(hits.is_empty());
+            let hits = db
+                .search_execution_history("drop table", 10, ("[", "]"))
+                .await
+                .unwrap();
+            assert_eq!(hits.len(), 1);
+            assert_eq!(hits[0].history.id, "exec_1");
+            assert!(hits[0].snippet.contains("[DROP] [TABLE]"));
+            // Terms shorter than the trigram index are rejected
+            assert!(db
+                .search_execution_history("OR", 10, ("[", "]"))
+                .await
+                .is_err());
+        });
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_database_history_filters() {
+        use models::ExecutionHistory;
+
+        let dir = std::env::temp_dir().join(format!(
+            "claude-scheduler-history-filters-{}",
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let db = database::Database::new(&dir.join("db.sqlite"))
+                .await
+                .unwrap();
+            for i in 0..4 {
+                db.create_execution_history(&ExecutionHistory {
+                    exit_code: Some(i),
+                    stdout: format!("run {i}"),
+                    finished_at: Some(format!("2025-01-0{} 12:0{i}:00", i + 1)),
+                    duration_ms: Some(i as u64 * 60_000),
+                    ..history_entry(
+                        &format!("exec_{i}"),
+                        &format!("2025-01-0{} 12:00:00", i + 1),
+                    )
+                })
+                .await
+                .unwrap();
+            }
+            // Runs from before durations were recorded never match
+            db.create_execution_history(&history_entry("exec_old", "2025-01-05 12:00:00"))
+                .await
+                .unwrap();
+
+            let history = db
+                .get_execution_history(&database::HistoryFilter {
+                    min_duration: Some(std::time::Duration::from_secs(120)),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            let ids: Vec<_> = history.iter().map(|h| h.id.as_str()).collect();
+            assert_eq!(ids, ["exec_3", "exec_2"]);
+
+            let history = db
+                .get_execution_history(&database::HistoryFilter {
+                    exit_code: Some(1),
+                    ..Default::default()
+                })
+                .await
+                .unwrap();
+            assert_eq!(history.len(), 1);
+            assert_eq!(history[0].duration_ms, Some(60_000));
+            assert_eq!(history[0].stdout, "run 1");
+        });
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_database_id_prefix() {
+        use models::Schedule;
+
+        let dir =
+            std::env::temp_dir().join(format!("claude-scheduler-prefix-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let db = database::Database::new(&dir.join("db.sqlite"))
+                .await
+                .unwrap();
+            for id in ["exec_1", "exec_2", "execX3"] {
+                db.create_execution_history(&history_entry(id, "2025-01-01 12:00:00"))
+                    .await
+                    .unwrap();
+            }
+            db.create_schedule(&Schedule {
+                id: "schedule_'; DELETE FROM schedules; --".to_string(),
+                ..Schedule::default()
+            })
+            .await
+            .unwrap();
+
+            // ID prefixes match `%` and `_` literally
+            let ids = |executions: Vec<models::ExecutionHistory>| -> Vec<String> {
+                let mut ids: Vec<_> = executions.into_iter().map(|h| h.id).collect();
+                ids.sort();
+                ids
+            };
+            assert_eq!(
+                ids(db.find_executions_by_prefix("exec_", 10).await.unwrap()),
+                ["exec_1", "exec_2"]
+            );
+            assert!(db
+                .find_executions_by_prefix("exec%", 10)
+                .await
+                .unwrap()
+                .is_empty());
+            assert_eq!(
+                db.find_executions_by_prefix("exec", 1).await.unwrap().len(),
+                1
+            );
+            assert_eq!(
+                db.find_schedules_by_prefix("schedule_'", 10)
+                    .await
+                    .unwrap()
+                    .len(),
+                1
+            );
+            assert!(db
+                .find_schedules_by_prefix("schedule_x", 10)
+                .await
+                .unwrap()
+                .is_empty());
+        });
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_database_output_lines() {
+        let dir =
+            std::env::temp_dir().join(format!("claude-scheduler-output-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let db = database::Database::new(&dir.join("db.sqlite"))
+                .await
+                .unwrap();
+            db.create_execution_history(&history_entry("exec_0", "2025-01-01 12:00:00"))
+                .await
+                .unwrap();
+
+            // Recorded output lines are filtered by stream, time and text
+            let line = |line, stream: &str, text: &str, written_at: &str| models::OutputLine {
+                line,
+                stream: stream.to_string(),
+                text: text.to_string(),
+                written_at: written_at.to_string(),
+            };
+            db.append_output_lines(
+                "exec_0",
+                &[
+                    line(1, "stdout", "100% done", "2025-01-01 12:00:00"),
+                    line(2, "stderr", "warning: it's 100%", "2025-01-01 12:00:05"),
+                    line(3, "stdout", "bye", "2025-01-01 12:00:10"),
+                ],
+            )
+            .await
+            .unwrap();
+            let texts = |lines: Vec<models::OutputLine>| -> Vec<String> {
+                lines.into_iter().map(|line| line.text).collect()
+            };
+            assert_eq!(
+                texts(
+                    db.get_output_lines("exec_0", &database::OutputFilter::default())
+                        .await
+                        .unwrap()
+                ),
+                ["100% done", "warning: it's 100%", "bye"]
+            );
+            let filter = database::OutputFilter {
+                contains: Some("100%".to_string()),
+                ..Default::default()
+            };
+            assert_eq!(
+                texts(db.get_output_lines("exec_0", &filter).await.unwrap()),
+                ["100% done", "warning: it's 100%"]
+            );
+            let filter = database::OutputFilter {
+                after_line: 1,
+                since: Some("2025-01-01 12:00:05".to_string()),
+                stream: Some(live_output::OutputStream::Stdout),
+                ..Default::default()
+            };
+            assert_eq!(
+                texts(db.get_output_lines("exec_0", &filter).await.unwrap()),
+                ["bye"]
+            );
+            assert!(db.has_output_lines("exec_0").await.unwrap());
+            assert!(!db.has_output_lines("exec_1").await.unwrap());
+        });
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_database_claim_due_schedule() {
+        use models::{Schedule, ScheduleStatus};
+
+        let dir =
+            std::env::temp_dir().join(format!("claude-scheduler-claim-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let db = database::Database::new(&dir.join("db.sqlite"))
+                .await
+                .unwrap();
+            let schedule = Schedule {
+                id: "schedule_1".to_string(),
+                scheduled_time: Some("2025-01-01T09:00".to_string()),
+                ..Schedule::default()
+            };
+            db.create_schedule(&schedule).await.unwrap();
+            let due_at = schedule.scheduled_time.as_deref();
+
+            // A run found due at another time is not claimed
+            assert!(!db
+                .claim_due_schedule(&schedule.id, "pid:1", Some("2025-01-01T08:00"))
+                .await
+                .unwrap());
+            assert!(db
+                .claim_due_schedule(&schedule.id, "pid:1", due_at)
+                .await
+                .unwrap());
+            assert!(!db
+                .claim_due_schedule(&schedule.id, "pid:2", due_at)
+                .await
+                .unwrap());
+
+            // Once the run has finished, the same due time is not run again
+            db.update_schedule_status(&schedule.id, ScheduleStatus::Completed)
+                .await
+                .unwrap();
+            assert!(!db
+                .claim_due_schedule(&schedule.id, "pid:2", due_at)
+                .await
+                .unwrap());
+        });
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_api_cancel_and_delete_schedule() {
+        use models::{Schedule, ScheduleStatus};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let dir = std::env::temp_dir().join(format!("claude-scheduler-api-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let db = database::Database::new(&dir.join("db.sqlite"))
+                .await
+                .unwrap();
+            for (id, status) in [
+                ("schedule_idle", ScheduleStatus::Pending),
+                ("schedule_running", ScheduleStatus::Running),
+            ] {
+                db.create_schedule(&Schedule {
+                    id: id.to_string(),
+                    status,
+                    ..Schedule::default()
+                })
+                .await
+                .unwrap();
+            }
+
+            let state = daemon::DaemonState::new(
+                db.clone(),
+                daemon::DaemonSettings::from_config(&config::Config::default(), None),
+            );
+            let listener = api::bind(0).await.unwrap();
+            let address = listener.local_addr().unwrap();
+            tokio::spawn(api::serve(listener, state));
+
+            // Status code of a request without a body
+            let request = |method: &'static str, path: &'static str| async move {
+                let mut stream = tokio::net::TcpStream::connect(address).await.unwrap();
+                let request = format!(
+                    "{method} {path} HTTP/1.1\r\nHost: localhost\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                );
+                stream.write_all(request.as_bytes()).await.unwrap();
+                let mut response = String::new();
+                stream.read_to_string(&mut response).await.unwrap();
+                response.split(' ').nth(1).unwrap().parse::<u16>().unwrap()
+            };
+
+            assert_eq!(request("POST", "/api/schedules/schedule_running/cancel").await, 409);
+            assert_eq!(request("DELETE", "/api/schedules/schedule_running").await, 409);
+            assert_eq!(request("POST", "/api/schedules/schedule_none/cancel").await, 404);
+
+            assert_eq!(request("POST", "/api/schedules/schedule_idle/cancel").await, 204);
+            let cancelled = db.get_schedule("schedule_idle").await.unwrap().unwrap();
+            assert_eq!(cancelled.status, ScheduleStatus::Cancelled);
+            assert_eq!(request("POST", "/api/schedules/schedule_idle/cancel").await, 409);
+
+            assert_eq!(request("DELETE", "/api/schedules/schedule_idle").await, 204);
+            assert!(db.get_schedule("schedule_idle").await.unwrap().is_none());
+            assert!(db.get_schedule("schedule_running").await.unwrap().is_some());
+        });
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_daemon_dependencies() {
+        use daemon::DependencyState;
+        use models::{
+            DependencyFailurePolicy, ExecutionHistory, ExecutionStatus, ExecutionType, Schedule,
+            ScheduleStatus,
+        };
+
+        let dir =
+            std::env::temp_dir().join(format!("claude-scheduler-depends-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            let db = database::Database::new(&dir.join("db.sqlite"))
+                .await
+                .unwrap();
+            let schedule = |id: &str, depends_on: &str, policy| Schedule {
+                id: id.to_string(),
+                command: "true".to_string(),
+                scheduled_time: Some("2025-01-01T09:00".to_string()),
+                is_shell_mode: true,
+                execution_path: dir.to_string_lossy().to_string(),
+                depends_on: vec![depends_on.to_string()],
+                on_dependency_failure: policy,
+                ..Schedule::default()
+            };
+            let run = |id: &str, executed_at: &str, status| ExecutionHistory {
+                id: format!("exec_{id}_{executed_at}"),
+                command: "true".to_string(),
+                executed_at: executed_at.to_string(),
+                execution_type: ExecutionType::FromSchedule,
+                status,
+                output: String::new(),
+                branch: "main".to_string(),
+                execution_path: ".".to_string(),
+                claude_skip_permissions: false,
+                claude_continue_from_last: false,
+                schedule_id: Some(id.to_string()),
+                attempt: Some(1),
+                exit_code: None,
+                batch_id: None,
+                stdout: String::new(),
+                stderr: String::new(),
+                finished_at: None,
+                duration_ms: None,
+            };
+
+            // A one-shot parent counts once it has finished
+            let parent = Schedule {
+                id: "schedule_once".to_string(),
+                ..Schedule::default()
+            };
+            db.create_schedule(&parent).await.unwrap();
+            let child = schedule("schedule_child", &parent.id, DependencyFailurePolicy::Skip);
+            let state = || daemon::dependency_state(&db, &child);
+            assert_eq!(state().await.unwrap(), DependencyState::Waiting);
+            db.update_schedule_status(&parent.id, ScheduleStatus::Completed)
+                .await
+                .unwrap();
+            assert_eq!(state().await.unwrap(), DependencyState::Ready);
+            db.update_schedule_status(&parent.id, ScheduleStatus::Failed)
+                .await
+                .unwrap();
+            assert_eq!(
+                state().await.unwrap(),
+                DependencyState::Failed(parent.id.clone())
+            );
+
+            // A recurring parent stays pending, its latest run since the
+            // child's time counts
+            let parent = Schedule {
+                id: "schedule_every".to_string(),
+                interval_minutes: Some(1),
+                ..Schedule::default()
+            };
+            db.create_schedule(&parent).await.unwrap();
+            let child = schedule("schedule_child", &parent.id, DependencyFailurePolicy::Skip);
+            let state = || daemon::dependency_state(&db, &child);
+            db.create_execution_history(&run(
+                &parent.id,
+                "2025-01-01 08:59:00",
+                ExecutionStatus::Success,
+            ))
+            .await
+            .unwrap();
+            assert_eq!(state().await.unwrap(), DependencyState::Waiting);
+            db.create_execution_history(&run(
+                &parent.id,
+                "2025-01-01 09:00:00",
+                ExecutionStatus::Failed,
+            ))
+            .await
+            .unwrap();
+            db.create_execution_history(&run(
+                &parent.id,
+                "2025-01-01 09:01:00",
+                ExecutionStatus::Skipped,
+            ))
+            .await
+            .unwrap();
+            assert_eq!(
+                state().await.unwrap(),
+                DependencyState::Failed(parent.id.clone())
+            );
+            db.create_execution_history(&run(
+                &parent.id,
+                "2025-01-01 09:02:00",
+                ExecutionStatus::Success,
+            ))
+            .await
+            .unwrap();
+            assert_eq!(state().await.unwrap(), DependencyState::Ready);
+
+            // Children of a failed parent follow their policy
+            let parent = Schedule {
+                id: "schedule_failed".to_string(),
+                status: ScheduleStatus::Failed,
+                ..Schedule::default()
+            };
+            db.create_schedule(&parent).await.unwrap();
+            for (id, policy) in [
+                ("schedule_skip", DependencyFailurePolicy::Skip),
+                ("schedule_run", DependencyFailurePolicy::Run),
+                ("schedule_wait", DependencyFailurePolicy::Wait),
+            ] {
+                db.create_schedule(&schedule(id, &parent.id, policy))
+                    .await
+                    .unwrap();
+            }
+            let state = daemon::DaemonState::new(
+                db.clone(),
+                daemon::DaemonSettings::from_config(&config::Config::default(), None),
+            );
+            daemon::run_due_schedules(&state).await.unwrap();
+            state.tasks.close();
+            state.tasks.wait().await;
+
+            let runs = |id: &str| {
+                let db = db.clone();
+                let id = id.to_string();
+                async move {
+                    db.get_execution_history(&database::HistoryFilter {
+                        schedule_id: Some(id),
+                        ..Default::default()
+                    })
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|run| run.status)
+                    .collect::<Vec<_>>()
+                }
+            };
+            assert_eq!(runs("schedule_skip").await, [ExecutionStatus::Skipped]);
+            assert_eq!(runs("schedule_run").await, [ExecutionStatus::Success]);
+            assert!(runs("schedule_wait").await.is_empty());
+            let waiting = db.get_schedule("schedule_wait").await.unwrap().unwrap();
+            assert_eq!(waiting.status, ScheduleStatus::Pending);
+        });
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cli_commands_timeout_after_output_closed() {
+        use models::ExecutionStatus;
+
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            // The timeout still applies once the command has closed its output
+            let outcome = cli_commands::execute_command_internal(
+                "exec >/dev/null 2>&1; sleep 10",
+                true,
+                ".",
+                false,
+                false,
+                cli_commands::ExecutionControl {
+                    timeout: Some(std::time::Duration::from_secs(1)),
+                    ..Default::default()
+                },
+            )
+            .await
+            .unwrap();
+            assert_eq!(outcome.status, ExecutionStatus::TimedOut);
+            assert!(outcome.duration < std::time::Duration::from_secs(5));
+        });
+    }
+
     #[test]
     fn test_utils_build_scheduled_time() {
         let scheduled_time = utils::build_scheduled_time(false, 12, 30);
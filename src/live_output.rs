@@ -0,0 +1,166 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+
+use crate::models::ExecutionStatus;
+
+/// Number of events a slow subscriber may fall behind before it skips ahead
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// Which pipe of the child process a line came from
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+impl OutputStream {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutputStream::Stdout => "stdout",
+            OutputStream::Stderr => "stderr",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum OutputEvent {
+    Line { stream: OutputStream, text: String },
+    Finished { status: ExecutionStatus },
+}
+
+/// Summary of an execution that is currently producing output
+#[derive(Debug, Clone, Serialize)]
+pub struct RunningExecution {
+    pub id: String,
+    pub command: String,
+    pub branch: String,
+    pub started_at: String,
+    /// Set once the command has been started
+    pub pid: Option<u32>,
+}
+
+struct LiveExecution {
+    info: RunningExecution,
+    cancel: CancellationToken,
+    events: Vec<OutputEvent>,
+    sender: broadcast::Sender<OutputEvent>,
+}
+
+/// Output of running executions, kept so that late subscribers can catch up
+/// before following new lines
+#[derive(Clone, Default)]
+pub struct LiveOutputs {
+    executions: Arc<Mutex<HashMap<String, LiveExecution>>>,
+}
+
+impl LiveOutputs {
+    /// Register a new running execution and return the sink its lines go to
+    pub fn start(&self, info: RunningExecution) -> OutputSink {
+        let id = info.id.clone();
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        self.executions.lock().unwrap().insert(
+            id.clone(),
+            LiveExecution {
+                info,
+                cancel: CancellationToken::new(),
+                events: Vec::new(),
+                sender,
+            },
+        );
+
+        OutputSink {
+            id,
+            outputs: self.clone(),
+        }
+    }
+
+    /// Everything the execution has produced so far plus a receiver for what follows
+    pub fn subscribe(
+        &self,
+        id: &str,
+    ) -> Option<(Vec<OutputEvent>, broadcast::Receiver<OutputEvent>)> {
+        let executions = self.executions.lock().unwrap();
+        executions
+            .get(id)
+            .map(|execution| (execution.events.clone(), execution.sender.subscribe()))
+    }
+
+    /// Ask a running execution to stop. Returns false if it is not running.
+    pub fn cancel(&self, id: &str) -> bool {
+        let executions = self.executions.lock().unwrap();
+        match executions.get(id) {
+            Some(execution) => {
+                execution.cancel.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn running(&self) -> Vec<RunningExecution> {
+        let executions = self.executions.lock().unwrap();
+        let mut running: Vec<RunningExecution> = executions
+            .values()
+            .map(|execution| execution.info.clone())
+            .collect();
+        running.sort_by(|a, b| a.started_at.cmp(&b.started_at));
+        running
+    }
+
+    fn publish(&self, id: &str, event: OutputEvent) {
+        if let Some(execution) = self.executions.lock().unwrap().get_mut(id) {
+            execution.events.push(event.clone());
+            // No subscribers is not an error
+            let _ = execution.sender.send(event);
+        }
+    }
+}
+
+/// Handle for publishing the output of one execution. Dropping it closes the
+/// stream for all subscribers.
+pub struct OutputSink {
+    id: String,
+    outputs: LiveOutputs,
+}
+
+impl OutputSink {
+    /// Cancelled when someone asks the execution to stop
+    pub fn cancellation(&self) -> CancellationToken {
+        let executions = self.outputs.executions.lock().unwrap();
+        executions
+            .get(&self.id)
+            .map(|execution| execution.cancel.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn set_pid(&self, pid: u32) {
+        if let Some(execution) = self.outputs.executions.lock().unwrap().get_mut(&self.id) {
+            execution.info.pid = Some(pid);
+        }
+    }
+
+    pub fn line(&self, stream: OutputStream, text: &str) {
+        self.outputs.publish(
+            &self.id,
+            OutputEvent::Line {
+                stream,
+                text: text.to_string(),
+            },
+        );
+    }
+
+    pub fn finish(self, status: ExecutionStatus) {
+        self.outputs
+            .publish(&self.id, OutputEvent::Finished { status });
+    }
+}
+
+impl Drop for OutputSink {
+    fn drop(&mut self) {
+        self.outputs.executions.lock().unwrap().remove(&self.id);
+    }
+}
@@ -0,0 +1,164 @@
+use anyhow::{bail, Result};
+use chrono::{DateTime, Local};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// 時間によるログローテーションの単位
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rotation {
+    Never,
+    Hourly,
+    Daily,
+}
+
+impl Rotation {
+    /// 同じファイルに書き込む期間を表すキー（キーが変わったらローテーション）
+    fn period(&self, time: DateTime<Local>) -> Option<String> {
+        match self {
+            Rotation::Never => None,
+            Rotation::Hourly => Some(time.format("%Y-%m-%d %H").to_string()),
+            Rotation::Daily => Some(time.format("%Y-%m-%d").to_string()),
+        }
+    }
+}
+
+impl FromStr for Rotation {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value.to_lowercase().as_str() {
+            "never" => Ok(Rotation::Never),
+            "hourly" => Ok(Rotation::Hourly),
+            "daily" => Ok(Rotation::Daily),
+            _ => bail!("Invalid log rotation '{value}'. Use never, hourly or daily"),
+        }
+    }
+}
+
+/// サイズと時間でローテーションするログファイル
+///
+/// ローテーション時は `daemon.log` → `daemon.log.1` → `daemon.log.2` … と
+/// 番号をずらし、`keep` 個を超えた古いファイルは削除する。
+pub struct RotatingFile {
+    path: PathBuf,
+    max_size: Option<u64>,
+    rotation: Rotation,
+    keep: usize,
+    file: File,
+    size: u64,
+    period: Option<String>,
+}
+
+impl RotatingFile {
+    pub fn open(
+        path: &Path,
+        max_size: Option<u64>,
+        rotation: Rotation,
+        keep: usize,
+    ) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let file = open_append(path)?;
+        let metadata = file.metadata()?;
+        // 再起動をまたいでも期間が変わっていればローテーションされるよう、既存ファイルの更新時刻を使う
+        let modified: DateTime<Local> = metadata
+            .modified()
+            .map(DateTime::from)
+            .unwrap_or_else(|_| Local::now());
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_size,
+            rotation,
+            keep,
+            file,
+            size: metadata.len(),
+            period: rotation.period(modified),
+        })
+    }
+
+    fn needs_rotation(&self, incoming: usize) -> bool {
+        if self.size == 0 {
+            return false;
+        }
+        let too_large = self
+            .max_size
+            .is_some_and(|max| self.size + incoming as u64 > max);
+        too_large || self.rotation.period(Local::now()) != self.period
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file.flush()?;
+
+        if self.keep == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            for index in (1..self.keep).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    std::fs::rename(&from, self.rotated_path(index + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        self.file = open_append(&self.path)?;
+        self.size = 0;
+        self.period = self.rotation.period(Local::now());
+        Ok(())
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{index}"));
+        PathBuf::from(path)
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.needs_rotation(buf.len()) {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn open_append(path: &Path) -> std::io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// デーモン用のロガーを初期化する
+///
+/// `RUST_LOG` が未指定の場合は info レベル以上を出力する。`log_file` が
+/// 指定されていればそこへ、なければ標準エラー出力へ書き込む。
+pub fn init_daemon_logger(log_file: Option<RotatingFile>) {
+    let mut builder = env_logger::Builder::from_env(
+        env_logger::Env::default().default_filter_or("info,sqlx=warn"),
+    );
+    builder.format(|buf, record| {
+        writeln!(
+            buf,
+            "{} {:<5} {}",
+            Local::now().format("%Y-%m-%d %H:%M:%S"),
+            record.level(),
+            record.args()
+        )
+    });
+
+    if let Some(log_file) = log_file {
+        builder.target(env_logger::Target::Pipe(Box::new(log_file)));
+    }
+
+    builder.init();
+}
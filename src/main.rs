@@ -1,37 +1,110 @@
+mod api;
+mod batch;
 mod cli;
 mod cli_commands;
 mod cli_handlers;
 #[cfg(feature = "gui")]
 mod components;
 mod config;
+mod cron;
+mod daemon;
+mod daemonize;
 mod database;
 mod git;
+mod live_output;
+mod logging;
+mod migrations;
 mod models;
-#[cfg(feature = "gui")]
 mod persistence;
+mod usage_limit;
 mod utils;
 
 use anyhow::Result;
 use clap::Parser;
 
+/// Move schedules and history saved by older GUI versions into the database
+async fn import_legacy_data(db: &database::Database) {
+    match persistence::import_legacy_data(db).await {
+        Ok(summary) if summary != persistence::ImportSummary::default() => eprintln!(
+            "📦 Imported {} schedules and {} history entries saved by the GUI",
+            summary.schedules, summary.history
+        ),
+        Ok(_) => {}
+        Err(e) => eprintln!("⚠️  GUI data not imported: {e:#}"),
+    }
+}
+
 fn main() -> Result<()> {
     // Check if running in CLI mode
     let args: Vec<String> = std::env::args().collect();
 
     if args.len() > 1 {
         // CLI mode
-        env_logger::init();
-
         let cli_args = cli::Cli::parse();
 
+        // Load configuration
+        let config = config::Config::load()?;
+        let database_path = std::path::absolute(config.database_path())?;
+
+        // The daemon locks its PID file and detaches before the runtime
+        // starts, because forking only carries the calling thread over
+        let _pid_file = match &cli_args.command {
+            cli::Commands::Daemon {
+                pid_file,
+                log_file,
+                log_max_size,
+                log_rotate,
+                log_keep,
+                detach,
+                ..
+            } => {
+                let pid_path = match pid_file {
+                    Some(path) => std::path::absolute(path)?,
+                    None => database_path.with_extension("pid"),
+                };
+                let mut pid_file = daemonize::PidFile::acquire(&pid_path)?;
+
+                // Open the log file while errors can still reach the terminal
+                let log_file = match log_file {
+                    Some(path) => {
+                        let max_size = utils::parse_size(log_max_size)?;
+                        Some(logging::RotatingFile::open(
+                            &std::path::absolute(path)?,
+                            (max_size > 0).then_some(max_size),
+                            log_rotate.parse()?,
+                            *log_keep,
+                        )?)
+                    }
+                    None => None,
+                };
+
+                if *detach {
+                    daemonize::detach()?;
+                }
+                pid_file.write_pid()?;
+                logging::init_daemon_logger(log_file);
+                Some(pid_file)
+            }
+            _ => {
+                env_logger::init();
+                None
+            }
+        };
+
         // Create a new runtime for CLI mode
         let runtime = tokio::runtime::Runtime::new()?;
         runtime.block_on(async {
-            // Load configuration
-            let config = config::Config::load().await?;
+            // `db migrate --status` has to see the database before it is migrated
+            if let cli::Commands::Db {
+                action: cli::DbAction::Migrate { status },
+            } = &cli_args.command
+            {
+                return cli_handlers::migrate_database(&database_path, *status).await;
+            }
 
             // Initialize database
-            let db = database::Database::new(&config.database_path()).await?;
+            let db = database::Database::new(&database_path).await?;
+            import_legacy_data(&db).await;
 
             match cli_args.command {
                 cli::Commands::Exec {
@@ -41,6 +114,7 @@ fn main() -> Result<()> {
                     worktree,
                     skip_permissions,
                     continue_from_last,
+                    timeout,
                 } => {
                     cli_commands::execute_command_immediate(
                         &command,
@@ -50,6 +124,32 @@ fn main() -> Result<()> {
                         cli_args.verbose,
                         skip_permissions,
                         continue_from_last,
+                        timeout.as_deref(),
+                    )
+                    .await?;
+                }
+                cli::Commands::Batch {
+                    file,
+                    mode,
+                    parallel,
+                    worktree,
+                    branch_prefix,
+                    skip_permissions,
+                    timeout,
+                    stop_on_failure,
+                } => {
+                    batch::run_batch(
+                        &db,
+                        &file,
+                        &batch::BatchOptions {
+                            mode: mode.as_deref(),
+                            parallel,
+                            worktree,
+                            branch_prefix: branch_prefix.as_deref(),
+                            skip_permissions,
+                            timeout: timeout.as_deref(),
+                            stop_on_failure,
+                        },
                     )
                     .await?;
                 }
@@ -57,27 +157,107 @@ fn main() -> Result<()> {
                     command,
                     time,
                     date,
+                    cron,
+                    every,
+                    until,
+                    count,
                     mode,
                     branch,
                     worktree,
                     memo,
                     skip_permissions,
                     continue_from_last,
+                    timeout,
+                    misfire,
+                    misfire_grace,
+                    max_attempts,
+                    retry_delay,
+                    retry_backoff,
+                    retry_on_exit,
+                    retry_on_output,
+                    continue_after_limit,
+                    depends_on,
+                    on_dependency_failure,
+                    if_new_commits,
+                    if_file_exists,
+                    if_command,
                 } => {
                     cli_commands::schedule_command(
                         &db,
-                        &command,
-                        &time,
-                        &date,
-                        &mode,
-                        branch.as_deref(),
-                        worktree,
-                        memo.as_deref(),
-                        skip_permissions,
-                        continue_from_last,
+                        &cli_commands::ScheduleOptions {
+                            command: &command,
+                            time: time.as_deref(),
+                            date: &date,
+                            cron: cron.as_deref(),
+                            every: every.as_deref(),
+                            until: until.as_deref(),
+                            count,
+                            mode: &mode,
+                            branch: branch.as_deref(),
+                            worktree,
+                            memo: memo.as_deref(),
+                            execution_path: None,
+                            skip_permissions,
+                            continue_from_last,
+                            timeout: timeout.as_deref(),
+                            misfire: misfire.as_deref(),
+                            misfire_grace: misfire_grace.as_deref(),
+                            max_attempts,
+                            retry_delay: retry_delay.as_deref(),
+                            retry_backoff,
+                            retry_on_exit: &retry_on_exit,
+                            retry_on_output: &retry_on_output,
+                            continue_after_limit,
+                            depends_on: &depends_on,
+                            on_dependency_failure: on_dependency_failure.as_deref(),
+                            if_new_commits,
+                            if_file_exists: &if_file_exists,
+                            if_command: &if_command,
+                        },
                     )
                     .await?;
                 }
+                cli::Commands::Cancel { id } => {
+                    cli_commands::cancel_command(&db, &id).await?;
+                }
+                cli::Commands::Delete { id } => {
+                    cli_commands::delete_command(&db, &id).await?;
+                }
+                cli::Commands::Edit {
+                    id,
+                    command,
+                    time,
+                    date,
+                    mode,
+                    branch,
+                    memo,
+                    timeout,
+                    skip_permissions,
+                    continue_from_last,
+                } => {
+                    cli_commands::edit_command(
+                        &db,
+                        &id,
+                        &cli_commands::ScheduleEdit {
+                            command: command.as_deref(),
+                            time: time.as_deref(),
+                            date: date.as_deref(),
+                            mode: mode.as_deref(),
+                            branch: branch.as_deref(),
+                            memo: memo.as_deref(),
+                            timeout: timeout.as_deref(),
+                            skip_permissions,
+                            continue_from_last,
+                        },
+                    )
+                    .await?;
+                }
+                cli::Commands::Reschedule { id, time, date } => {
+                    cli_commands::reschedule_command(&db, &id, &time, &date).await?;
+                }
+                cli::Commands::Kill { execution_id } => {
+                    cli_commands::kill_command(&db, &execution_id).await?;
+                }
                 cli::Commands::List {
                     status,
                     format,
@@ -93,6 +273,8 @@ fn main() -> Result<()> {
                     limit,
                     from,
                     to,
+                    min_duration,
+                    exit_code,
                 } => {
                     cli_handlers::show_history(
                         &db,
@@ -103,31 +285,51 @@ fn main() -> Result<()> {
                         limit,
                         from,
                         to,
+                        min_duration.as_deref(),
+                        exit_code,
                     )
                     .await?;
                 }
-                cli::Commands::Daemon {
-                    port,
-                    interval,
-                    pid_file,
-                    log_file,
-                    detach,
+                cli::Commands::Search {
+                    query,
+                    format,
+                    limit,
                 } => {
-                    if detach {
-                        // TODO: Implement proper daemonization
-                        eprintln!("Detach mode not yet implemented. Running in foreground.");
-                    }
-
-                    cli_handlers::run_daemon(
+                    cli_handlers::search_history(&db, &query, &format, limit).await?;
+                }
+                cli::Commands::Show {
+                    id,
+                    output_only,
+                    no_pager,
+                } => {
+                    cli_handlers::show_entry(&db, &id, output_only, no_pager).await?;
+                }
+                cli::Commands::Logs {
+                    execution_id,
+                    follow,
+                    since,
+                    grep,
+                    stderr,
+                } => {
+                    cli_handlers::show_logs(
                         &db,
-                        port,
-                        interval,
-                        pid_file.as_deref(),
-                        log_file.as_deref(),
-                        detach,
+                        &execution_id,
+                        follow,
+                        since.as_deref(),
+                        grep.as_deref(),
+                        stderr,
                     )
                     .await?;
                 }
+                cli::Commands::Graph { format } => {
+                    cli_handlers::show_graph(&db, &format).await?;
+                }
+                cli::Commands::Daemon { port, interval, .. } => {
+                    cli_handlers::run_daemon(&db, port, interval, &config)
+                        .await
+                        .inspect_err(|e| log::error!("daemon stopped: error={e:#}"))?;
+                }
+                cli::Commands::Db { .. } => unreachable!("handled before the database is opened"),
                 cli::Commands::Config { action } => match action {
                     cli::ConfigAction::Show => {
                         let all_config = db.get_all_config().await?;
@@ -0,0 +1,399 @@
+use anyhow::{bail, Result};
+use chrono::Local;
+use sqlx::sqlite::{SqliteConnection, SqlitePool};
+
+/// A change to the database schema.
+///
+/// Migrations are applied in order at startup, each in its own transaction
+/// together with its `schema_version` row. A released migration must never be
+/// changed; schema changes are made by adding a new one at the end.
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    sql: &'static str,
+}
+
+/// Every migration this binary knows, oldest first
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "initial schema",
+        sql: r#"
+        CREATE TABLE IF NOT EXISTS schedules (
+            id TEXT PRIMARY KEY,
+            command TEXT NOT NULL,
+            scheduled_time TEXT,
+            memo TEXT,
+            created_at TEXT NOT NULL,
+            status TEXT NOT NULL,
+            is_shell_mode INTEGER NOT NULL,
+            branch TEXT NOT NULL,
+            execution_path TEXT NOT NULL DEFAULT '.',
+            claude_skip_permissions INTEGER NOT NULL DEFAULT 0,
+            claude_continue_from_last INTEGER NOT NULL DEFAULT 0,
+            cron_expression TEXT,
+            interval_minutes INTEGER,
+            max_runs INTEGER,
+            repeat_until TEXT,
+            run_count INTEGER NOT NULL DEFAULT 0,
+            timeout_seconds INTEGER,
+            misfire_policy TEXT NOT NULL DEFAULT 'run_once',
+            misfire_grace_seconds INTEGER,
+            retry_max_attempts INTEGER,
+            retry_delay_seconds INTEGER,
+            retry_multiplier REAL,
+            retry_on_exit_codes TEXT NOT NULL DEFAULT '[]',
+            retry_on_output TEXT NOT NULL DEFAULT '[]',
+            continue_after_limit INTEGER NOT NULL DEFAULT 0,
+            depends_on TEXT NOT NULL DEFAULT '[]',
+            on_dependency_failure TEXT NOT NULL DEFAULT 'skip',
+            conditions TEXT NOT NULL DEFAULT '[]',
+            last_commit TEXT,
+            claimed_by TEXT,
+            started_at TEXT
+        );
+
+        CREATE TABLE IF NOT EXISTS execution_history (
+            id TEXT PRIMARY KEY,
+            command TEXT NOT NULL,
+            executed_at TEXT NOT NULL,
+            execution_type TEXT NOT NULL,
+            status TEXT NOT NULL,
+            output TEXT NOT NULL,
+            branch TEXT NOT NULL,
+            execution_path TEXT NOT NULL DEFAULT '.',
+            claude_skip_permissions INTEGER NOT NULL DEFAULT 0,
+            claude_continue_from_last INTEGER NOT NULL DEFAULT 0,
+            schedule_id TEXT,
+            attempt INTEGER,
+            exit_code INTEGER,
+            batch_id TEXT
+        );
+
+        -- Executions a daemon is running right now, so that other processes
+        -- can ask for them to be stopped
+        CREATE TABLE IF NOT EXISTS running_executions (
+            execution_id TEXT PRIMARY KEY,
+            schedule_id TEXT,
+            command TEXT NOT NULL,
+            pid INTEGER,
+            daemon_id TEXT NOT NULL,
+            started_at TEXT NOT NULL,
+            cancel_requested INTEGER NOT NULL DEFAULT 0
+        );
+
+        CREATE TABLE IF NOT EXISTS configuration (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        );
+    "#,
+    },
+    Migration {
+        version: 2,
+        description: "full-text search over execution output",
+        // The trigram tokenizer matches any substring of 3 or more characters,
+        // which also works for Japanese text without spaces between words
+        sql: r#"
+        CREATE VIRTUAL TABLE execution_history_fts USING fts5(
+            id UNINDEXED,
+            command,
+            output,
+            tokenize = 'trigram'
+        );
+
+        INSERT INTO execution_history_fts (id, command, output)
+            SELECT id, command, output FROM execution_history;
+
+        CREATE TRIGGER execution_history_fts_insert AFTER INSERT ON execution_history BEGIN
+            INSERT INTO execution_history_fts (id, command, output)
+                VALUES (new.id, new.command, new.output);
+        END;
+
+        CREATE TRIGGER execution_history_fts_delete AFTER DELETE ON execution_history BEGIN
+            DELETE FROM execution_history_fts WHERE id = old.id;
+        END;
+
+        CREATE TRIGGER execution_history_fts_update AFTER UPDATE OF command, output ON execution_history BEGIN
+            DELETE FROM execution_history_fts WHERE id = old.id;
+            INSERT INTO execution_history_fts (id, command, output)
+                VALUES (new.id, new.command, new.output);
+        END;
+    "#,
+    },
+    Migration {
+        version: 3,
+        description: "separate stdout and stderr, finish time and duration",
+        sql: r#"
+        ALTER TABLE execution_history ADD COLUMN stdout TEXT NOT NULL DEFAULT '';
+        ALTER TABLE execution_history ADD COLUMN stderr TEXT NOT NULL DEFAULT '';
+        ALTER TABLE execution_history ADD COLUMN finished_at TEXT;
+        ALTER TABLE execution_history ADD COLUMN duration_ms INTEGER;
+    "#,
+    },
+    Migration {
+        version: 4,
+        description: "output lines of executions",
+        sql: r#"
+        -- Written while the daemon runs a command, so that `logs` can follow it
+        CREATE TABLE execution_output (
+            execution_id TEXT NOT NULL,
+            line INTEGER NOT NULL,
+            stream TEXT NOT NULL,
+            text TEXT NOT NULL,
+            written_at TEXT NOT NULL,
+            PRIMARY KEY (execution_id, line)
+        );
+
+        CREATE TRIGGER execution_output_delete AFTER DELETE ON execution_history BEGIN
+            DELETE FROM execution_output WHERE execution_id = old.id;
+        END;
+    "#,
+    },
+];
+
+/// Columns that databases created before versioned migrations may lack.
+/// They are added before the initial schema is recorded for such a database.
+const LEGACY_COLUMNS: &[(&str, &str, &str)] = &[
+    ("schedules", "execution_path", "TEXT NOT NULL DEFAULT '.'"),
+    (
+        "schedules",
+        "claude_skip_permissions",
+        "INTEGER NOT NULL DEFAULT 0",
+    ),
+    (
+        "schedules",
+        "claude_continue_from_last",
+        "INTEGER NOT NULL DEFAULT 0",
+    ),
+    ("schedules", "cron_expression", "TEXT"),
+    ("schedules", "interval_minutes", "INTEGER"),
+    ("schedules", "max_runs", "INTEGER"),
+    ("schedules", "repeat_until", "TEXT"),
+    ("schedules", "run_count", "INTEGER NOT NULL DEFAULT 0"),
+    ("schedules", "timeout_seconds", "INTEGER"),
+    ("schedules", "claimed_by", "TEXT"),
+    ("schedules", "started_at", "TEXT"),
+    (
+        "schedules",
+        "misfire_policy",
+        "TEXT NOT NULL DEFAULT 'run_once'",
+    ),
+    ("schedules", "misfire_grace_seconds", "INTEGER"),
+    ("schedules", "retry_max_attempts", "INTEGER"),
+    ("schedules", "retry_delay_seconds", "INTEGER"),
+    ("schedules", "retry_multiplier", "REAL"),
+    (
+        "schedules",
+        "retry_on_exit_codes",
+        "TEXT NOT NULL DEFAULT '[]'",
+    ),
+    ("schedules", "retry_on_output", "TEXT NOT NULL DEFAULT '[]'"),
+    (
+        "schedules",
+        "continue_after_limit",
+        "INTEGER NOT NULL DEFAULT 0",
+    ),
+    ("schedules", "depends_on", "TEXT NOT NULL DEFAULT '[]'"),
+    (
+        "schedules",
+        "on_dependency_failure",
+        "TEXT NOT NULL DEFAULT 'skip'",
+    ),
+    ("schedules", "conditions", "TEXT NOT NULL DEFAULT '[]'"),
+    ("schedules", "last_commit", "TEXT"),
+    (
+        "execution_history",
+        "execution_path",
+        "TEXT NOT NULL DEFAULT '.'",
+    ),
+    (
+        "execution_history",
+        "claude_skip_permissions",
+        "INTEGER NOT NULL DEFAULT 0",
+    ),
+    (
+        "execution_history",
+        "claude_continue_from_last",
+        "INTEGER NOT NULL DEFAULT 0",
+    ),
+    ("execution_history", "schedule_id", "TEXT"),
+    ("execution_history", "attempt", "INTEGER"),
+    ("execution_history", "exit_code", "INTEGER"),
+    ("execution_history", "batch_id", "TEXT"),
+];
+
+/// Schema version of the newest migration this binary knows
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+/// A migration as seen by `db migrate --status`
+#[derive(Debug, Clone)]
+pub struct MigrationState {
+    pub version: i64,
+    pub description: String,
+    /// `None` while the migration is pending
+    pub applied_at: Option<String>,
+    /// Applied by a newer claude-scheduler than this one
+    pub unknown: bool,
+}
+
+/// Schema version of a database, 0 when it has never been migrated
+pub async fn current_version(pool: &SqlitePool) -> Result<i64> {
+    let mut connection = pool.acquire().await?;
+    version_of(&mut connection).await
+}
+
+/// Bail out if the database was migrated by a newer claude-scheduler
+pub async fn ensure_supported(pool: &SqlitePool) -> Result<()> {
+    let version = current_version(pool).await?;
+    if version > latest_version() {
+        bail!(
+            "The database has schema version {version}, but this claude-scheduler only supports up to version {}. Upgrade claude-scheduler to use it.",
+            latest_version()
+        );
+    }
+    Ok(())
+}
+
+/// Apply the pending migrations and return them
+pub async fn migrate(pool: &SqlitePool) -> Result<Vec<&'static Migration>> {
+    ensure_supported(pool).await?;
+    if current_version(pool).await? == latest_version() {
+        return Ok(Vec::new());
+    }
+
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS {
+        // BEGIN IMMEDIATE takes the write lock up front, so that a daemon and
+        // a CLI starting at the same time do not both apply a migration
+        let mut transaction = pool.begin_with("BEGIN IMMEDIATE").await?;
+        create_version_table(&mut transaction).await?;
+
+        let version = version_of(&mut transaction).await?;
+        if migration.version <= version {
+            continue;
+        }
+        if version == 0 {
+            add_legacy_columns(&mut transaction).await?;
+        }
+
+        sqlx::raw_sql(migration.sql)
+            .execute(&mut *transaction)
+            .await?;
+        sqlx::query(
+            "INSERT INTO schema_version (version, description, applied_at) VALUES (?, ?, ?)",
+        )
+        .bind(migration.version)
+        .bind(migration.description)
+        .bind(Local::now().format("%Y-%m-%d %H:%M:%S").to_string())
+        .execute(&mut *transaction)
+        .await?;
+
+        transaction.commit().await?;
+        applied.push(migration);
+    }
+
+    Ok(applied)
+}
+
+/// Every known migration with when it was applied, followed by versions
+/// applied by a newer claude-scheduler
+pub async fn status(pool: &SqlitePool) -> Result<Vec<MigrationState>> {
+    let mut connection = pool.acquire().await?;
+    let applied: Vec<(i64, String, String)> = if has_version_table(&mut connection).await? {
+        sqlx::query_as(
+            "SELECT version, description, applied_at FROM schema_version ORDER BY version",
+        )
+        .fetch_all(&mut *connection)
+        .await?
+    } else {
+        Vec::new()
+    };
+
+    let mut states: Vec<MigrationState> = MIGRATIONS
+        .iter()
+        .map(|migration| MigrationState {
+            version: migration.version,
+            description: migration.description.to_string(),
+            applied_at: applied
+                .iter()
+                .find(|(version, _, _)| *version == migration.version)
+                .map(|(_, _, applied_at)| applied_at.clone()),
+            unknown: false,
+        })
+        .collect();
+
+    states.extend(
+        applied
+            .into_iter()
+            .filter(|(version, _, _)| *version > latest_version())
+            .map(|(version, description, applied_at)| MigrationState {
+                version,
+                description,
+                applied_at: Some(applied_at),
+                unknown: true,
+            }),
+    );
+
+    Ok(states)
+}
+
+async fn create_version_table(connection: &mut SqliteConnection) -> Result<()> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(connection)
+    .await?;
+
+    Ok(())
+}
+
+async fn has_version_table(connection: &mut SqliteConnection) -> Result<bool> {
+    let tables = sqlx::query_as::<_, (String,)>(
+        "SELECT name FROM sqlite_master WHERE type = 'table' AND name = 'schema_version'",
+    )
+    .fetch_all(connection)
+    .await?;
+
+    Ok(!tables.is_empty())
+}
+
+async fn version_of(connection: &mut SqliteConnection) -> Result<i64> {
+    if !has_version_table(&mut *connection).await? {
+        return Ok(0);
+    }
+
+    let (version,) = sqlx::query_as::<_, (Option<i64>,)>("SELECT MAX(version) FROM schema_version")
+        .fetch_one(&mut *connection)
+        .await?;
+    Ok(version.unwrap_or(0))
+}
+
+/// Bring the tables of a database created before versioned migrations up to
+/// the initial schema. Tables that do not exist yet are left to the migration.
+async fn add_legacy_columns(connection: &mut SqliteConnection) -> Result<()> {
+    for (table, column, definition) in LEGACY_COLUMNS {
+        let columns = sqlx::query_as::<_, (String,)>("SELECT name FROM pragma_table_info(?)")
+            .bind(table)
+            .fetch_all(&mut *connection)
+            .await?;
+
+        if !columns.is_empty() && !columns.iter().any(|(name,)| name == column) {
+            // Identifiers cannot be bound; these all come from LEGACY_COLUMNS
+            sqlx::query(&format!(
+                "ALTER TABLE {table} ADD COLUMN {column} {definition}"
+            ))
+            .execute(&mut *connection)
+            .await?;
+        }
+    }
+
+    Ok(())
+}
@@ -1,6 +1,8 @@
-use chrono::Local;
+use chrono::{Duration, Local, NaiveDateTime};
 use serde::{Deserialize, Serialize};
 
+use crate::cron::CronExpression;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Schedule {
     pub id: String,
@@ -16,15 +18,107 @@ pub struct Schedule {
     pub claude_skip_permissions: bool, // --dangerously-skip-permissions フラグ
     #[serde(default)]
     pub claude_continue_from_last: bool, // -c フラグ
+    #[serde(default)]
+    pub cron_expression: Option<String>, // 繰り返し実行用のcron式 ("0 3 * * 1-5", "@daily" など)
+    #[serde(default)]
+    pub interval_minutes: Option<u32>, // 一定間隔での繰り返し実行（分）
+    #[serde(default)]
+    pub max_runs: Option<u32>, // 繰り返し実行の最大回数
+    #[serde(default)]
+    pub repeat_until: Option<String>, // 繰り返し実行の終了日時 "2024-01-01T18:00" 形式
+    #[serde(default)]
+    pub run_count: u32, // これまでの実行回数
+    #[serde(default)]
+    pub timeout_seconds: Option<u32>, // 実行のタイムアウト（秒）。超えたらプロセスグループごと終了する
+    #[serde(default)]
+    pub misfire_policy: MisfirePolicy, // 予定時刻を過ぎてから実行する場合の扱い
+    #[serde(default)]
+    pub misfire_grace_seconds: Option<u32>, // Skip時に遅れを許容する秒数
+    #[serde(default)]
+    pub retry_max_attempts: Option<u32>, // 失敗時に再試行する場合の最大試行回数（初回を含む）
+    #[serde(default)]
+    pub retry_delay_seconds: Option<u32>, // 最初の再試行までの待ち時間（秒）
+    #[serde(default)]
+    pub retry_multiplier: Option<f64>, // 再試行ごとに待ち時間に掛ける倍率
+    #[serde(default)]
+    pub retry_on_exit_codes: Vec<i32>, // 再試行する終了コード（空ならすべての失敗）
+    #[serde(default)]
+    pub retry_on_output: Vec<String>, // 出力に含まれていたら再試行する文字列（空ならすべての失敗）
+    #[serde(default)]
+    pub continue_after_limit: bool, // 利用制限の解除後の再実行で前回のセッションから継続する
+    #[serde(default)]
+    pub depends_on: Vec<String>, // 先に成功している必要があるスケジュールのID
+    #[serde(default)]
+    pub on_dependency_failure: DependencyFailurePolicy, // 依存先が失敗した場合の扱い
+    #[serde(default)]
+    pub conditions: Vec<ScheduleCondition>, // 実行する条件（すべて満たす場合のみ実行）
+    #[serde(default)]
+    pub last_commit: Option<String>, // 前回の実行後のHEADのコミット（new_commits条件用）
+    #[serde(default)]
+    pub claimed_by: Option<String>, // 実行中のデーモン（実行中のみ）
+    #[serde(default)]
+    pub started_at: Option<String>, // 実行開始日時 "2024-01-01 10:00:00" 形式（実行中のみ）
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ScheduleStatus {
     Pending,
+    Running,
+    /// Claudeの利用制限で止まり、制限の解除後に再実行を待っている
+    RateLimited,
     Completed,
     Failed,
+    /// 手動でキャンセルされ、実行されない
+    Cancelled,
+}
+
+/// デーモンの停止中やスリープ中に予定時刻を過ぎたスケジュールの扱い
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MisfirePolicy {
+    /// 遅れても1回だけ実行する（繰り返しスケジュールは次の予定時刻に進む）
+    #[default]
+    RunOnce,
+    /// 猶予時間より遅れた実行はスキップして履歴に記録する
+    Skip,
+    /// 繰り返しスケジュールの逃した実行をすべて順番に実行する
+    RunAll,
+}
+
+/// 依存先のスケジュールが失敗した場合の扱い
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyFailurePolicy {
+    /// 実行せずにスキップとして記録する（依存元にも伝わる）
+    #[default]
+    Skip,
+    /// 依存先の結果に関係なく実行する
+    Run,
+    /// 依存先が再実行されて成功するまで待つ
+    Wait,
+}
+
+/// 予定時刻に実行する前に確認する条件
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScheduleCondition {
+    /// 前回の実行後に実行ディレクトリのHEADが進んでいる（初回は常に満たす）
+    NewCommits,
+    /// ファイルが存在する（相対パスは実行ディレクトリから）
+    FileExists { path: String },
+    /// 実行ディレクトリで実行したコマンドが終了コード0で終わる
+    Command { command: String },
 }
 
+/// Skipで猶予時間の指定がない場合に許容する遅れ（秒）
+pub const DEFAULT_MISFIRE_GRACE_SECONDS: u32 = 300;
+
+/// 再試行の待ち時間の指定がない場合の最初の待ち時間（秒）
+pub const DEFAULT_RETRY_DELAY_SECONDS: u32 = 60;
+
+/// 再試行の倍率の指定がない場合の倍率
+pub const DEFAULT_RETRY_MULTIPLIER: f64 = 2.0;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecutionHistory {
     pub id: String,
@@ -39,6 +133,54 @@ pub struct ExecutionHistory {
     pub claude_skip_permissions: bool, // --dangerously-skip-permissions フラグ
     #[serde(default)]
     pub claude_continue_from_last: bool, // -c フラグ
+    #[serde(default)]
+    pub schedule_id: Option<String>, // 実行元のスケジュール
+    #[serde(default)]
+    pub attempt: Option<u32>, // スケジュール実行の試行回数（1から）
+    #[serde(default)]
+    pub exit_code: Option<i32>, // コマンドの終了コード（終了させた場合はNone）
+    #[serde(default)]
+    pub batch_id: Option<String>, // batchコマンドでまとめて実行した場合のバッチID
+    #[serde(default)]
+    pub stdout: String, // 標準出力のみ（outputは標準エラー出力と届いた順に混ざったもの）
+    #[serde(default)]
+    pub stderr: String, // 標準エラー出力のみ
+    #[serde(default)]
+    pub finished_at: Option<String>, // 終了時刻（開始時刻はexecuted_at）
+    #[serde(default)]
+    pub duration_ms: Option<u64>, // 実行時間（ミリ秒）
+}
+
+/// 全文検索で見つかった実行履歴
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    #[serde(flatten)]
+    pub history: ExecutionHistory,
+    pub snippet: String, // 一致した箇所の前後（一致部分は指定した記号で囲まれる）
+}
+
+/// 実行中にデーモンが記録した出力の1行（`execution_output` テーブル）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputLine {
+    pub line: u64,      // 実行ごとの行番号（1から）
+    pub stream: String, // "stdout" または "stderr"
+    pub text: String,
+    pub written_at: String, // "2024-01-01 10:00:00" 形式
+}
+
+/// デーモンが実行中のコマンド（`running_executions` テーブル）
+///
+/// 別プロセスの `kill` コマンドはここに停止を依頼し、デーモンが応答しない
+/// 場合はPIDのプロセスグループを直接終了させる。
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionProcess {
+    pub execution_id: String,
+    pub schedule_id: Option<String>,
+    pub command: String,
+    pub pid: Option<u32>,       // 子プロセス（プロセスグループのリーダー）のPID
+    pub daemon_id: String,      // 実行しているデーモン "pid:1234" 形式
+    pub started_at: String,     // "2024-01-01 10:00:00" 形式
+    pub cancel_requested: bool, // 停止が依頼されている
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -54,6 +196,16 @@ pub enum ExecutionType {
 pub enum ExecutionStatus {
     Success,
     Failed,
+    /// コマンドの終了前にデーモンが停止した
+    Interrupted,
+    /// タイムアウトを超えたため強制終了した
+    TimedOut,
+    /// 実行せずにスキップした（予定時刻の超過、依存先の失敗、条件の不成立）
+    Skipped,
+    /// Claudeの利用制限に達して止まった
+    RateLimited,
+    /// 要求により停止した（`kill` コマンド、API、GUIの停止ボタン）
+    Cancelled,
 }
 
 impl Default for Schedule {
@@ -73,16 +225,175 @@ impl Default for Schedule {
                 .to_string(),
             claude_skip_permissions: false,
             claude_continue_from_last: false,
+            cron_expression: None,
+            interval_minutes: None,
+            max_runs: None,
+            repeat_until: None,
+            run_count: 0,
+            timeout_seconds: None,
+            misfire_policy: MisfirePolicy::RunOnce,
+            misfire_grace_seconds: None,
+            retry_max_attempts: None,
+            retry_delay_seconds: None,
+            retry_multiplier: None,
+            retry_on_exit_codes: Vec::new(),
+            retry_on_output: Vec::new(),
+            continue_after_limit: false,
+            depends_on: Vec::new(),
+            on_dependency_failure: DependencyFailurePolicy::Skip,
+            conditions: Vec::new(),
+            last_commit: None,
+            claimed_by: None,
+            started_at: None,
         }
     }
 }
 
+impl Schedule {
+    /// 繰り返しスケジュールかどうか
+    pub fn is_recurring(&self) -> bool {
+        self.cron_expression.is_some() || self.interval_minutes.is_some()
+    }
+
+    /// 残り実行回数（回数制限がない場合はNone）
+    pub fn remaining_runs(&self) -> Option<u32> {
+        self.max_runs
+            .map(|max_runs| max_runs.saturating_sub(self.run_count))
+    }
+
+    /// 予定時刻を日時として取得
+    pub fn scheduled_at(&self) -> Option<NaiveDateTime> {
+        self.scheduled_time
+            .as_deref()
+            .and_then(|time| NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M").ok())
+    }
+
+    /// Skipで許容する遅れ
+    pub fn misfire_grace(&self) -> Duration {
+        Duration::seconds(
+            self.misfire_grace_seconds
+                .unwrap_or(DEFAULT_MISFIRE_GRACE_SECONDS)
+                .into(),
+        )
+    }
+
+    /// 失敗した試行を再試行するかどうか
+    ///
+    /// 中断・スキップされた実行は再試行しない。終了コードや出力の条件がある場合は
+    /// どれかに一致した失敗だけを再試行する。
+    pub fn should_retry(&self, history: &ExecutionHistory, attempt: u32) -> bool {
+        let Some(max_attempts) = self.retry_max_attempts else {
+            return false;
+        };
+        if attempt >= max_attempts
+            || !matches!(
+                history.status,
+                ExecutionStatus::Failed | ExecutionStatus::TimedOut
+            )
+        {
+            return false;
+        }
+        if self.retry_on_exit_codes.is_empty() && self.retry_on_output.is_empty() {
+            return true;
+        }
+
+        history
+            .exit_code
+            .is_some_and(|code| self.retry_on_exit_codes.contains(&code))
+            || self
+                .retry_on_output
+                .iter()
+                .any(|pattern| history.output.contains(pattern.as_str()))
+    }
+
+    /// 指定した試行が失敗した後、次の試行までの待ち時間
+    pub fn retry_delay(&self, attempt: u32) -> std::time::Duration {
+        let delay = self
+            .retry_delay_seconds
+            .unwrap_or(DEFAULT_RETRY_DELAY_SECONDS);
+        let multiplier = self.retry_multiplier.unwrap_or(DEFAULT_RETRY_MULTIPLIER);
+        let exponent = i32::try_from(attempt.saturating_sub(1)).unwrap_or(i32::MAX);
+        std::time::Duration::try_from_secs_f64(f64::from(delay) * multiplier.powi(exponent))
+            .unwrap_or(std::time::Duration::MAX)
+    }
+
+    /// 繰り返しスケジュールの次回実行時刻を"YYYY-MM-DDTHH:MM"形式で取得
+    ///
+    /// 実行回数の上限や終了日時に達している場合はNoneを返す。
+    pub fn next_occurrence(&self, after: NaiveDateTime) -> Option<String> {
+        if self.remaining_runs() == Some(0) {
+            return None;
+        }
+
+        let next = if let Some(expression) = &self.cron_expression {
+            let expression: CronExpression = expression.parse().ok()?;
+            expression.next_after(after)?
+        } else {
+            // 前回の予定時刻を基準にして実行間隔を保つ
+            let interval = Duration::minutes(i64::from(self.interval_minutes?.max(1)));
+            let mut next = self
+                .scheduled_at()
+                .unwrap_or(after)
+                .checked_add_signed(interval)?;
+            while next <= after {
+                next = next.checked_add_signed(interval)?;
+            }
+            next
+        };
+
+        if let Some(until) = self
+            .repeat_until
+            .as_deref()
+            .and_then(|until| NaiveDateTime::parse_from_str(until, "%Y-%m-%dT%H:%M").ok())
+        {
+            if next > until {
+                return None;
+            }
+        }
+
+        Some(next.format("%Y-%m-%dT%H:%M").to_string())
+    }
+
+    /// 繰り返し設定の表示用文字列（"0 3 * * 1-5", "every 45m until 2024-01-01T18:00" など）
+    pub fn repeat_description(&self) -> String {
+        let mut description = if let Some(expression) = &self.cron_expression {
+            expression.clone()
+        } else if let Some(minutes) = self.interval_minutes {
+            format!(
+                "every {}",
+                crate::utils::format_duration(Duration::minutes(i64::from(minutes)))
+            )
+        } else {
+            return String::new();
+        };
+
+        if let Some(until) = &self.repeat_until {
+            description.push_str(&format!(" until {until}"));
+        }
+
+        description
+    }
+}
+
 impl std::fmt::Display for ScheduleStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ScheduleStatus::Pending => write!(f, "待機中"),
+            ScheduleStatus::Running => write!(f, "実行中"),
+            ScheduleStatus::RateLimited => write!(f, "利用制限待ち"),
             ScheduleStatus::Completed => write!(f, "完了"),
             ScheduleStatus::Failed => write!(f, "失敗"),
+            ScheduleStatus::Cancelled => write!(f, "キャンセル"),
+        }
+    }
+}
+
+impl std::fmt::Display for ScheduleCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScheduleCondition::NewCommits => write!(f, "new commits"),
+            ScheduleCondition::FileExists { path } => write!(f, "file exists: {path}"),
+            ScheduleCondition::Command { command } => write!(f, "command: {command}"),
         }
     }
 }
@@ -103,6 +414,11 @@ impl std::fmt::Display for ExecutionStatus {
         match self {
             ExecutionStatus::Success => write!(f, "成功"),
             ExecutionStatus::Failed => write!(f, "失敗"),
+            ExecutionStatus::Interrupted => write!(f, "中断"),
+            ExecutionStatus::TimedOut => write!(f, "タイムアウト"),
+            ExecutionStatus::Skipped => write!(f, "スキップ"),
+            ExecutionStatus::RateLimited => write!(f, "利用制限"),
+            ExecutionStatus::Cancelled => write!(f, "キャンセル"),
         }
     }
 }
@@ -112,16 +428,22 @@ impl ScheduleStatus {
     pub fn to_db_string(&self) -> String {
         match self {
             ScheduleStatus::Pending => "pending".to_string(),
+            ScheduleStatus::Running => "running".to_string(),
+            ScheduleStatus::RateLimited => "rate_limited".to_string(),
             ScheduleStatus::Completed => "completed".to_string(),
             ScheduleStatus::Failed => "failed".to_string(),
+            ScheduleStatus::Cancelled => "cancelled".to_string(),
         }
     }
 
     pub fn from_string(s: &str) -> Self {
         match s.to_lowercase().as_str() {
             "pending" => ScheduleStatus::Pending,
+            "running" => ScheduleStatus::Running,
+            "rate_limited" => ScheduleStatus::RateLimited,
             "completed" => ScheduleStatus::Completed,
             "failed" => ScheduleStatus::Failed,
+            "cancelled" | "canceled" => ScheduleStatus::Cancelled,
             _ => ScheduleStatus::Pending,
         }
     }
@@ -153,6 +475,11 @@ impl ExecutionStatus {
         match self {
             ExecutionStatus::Success => "success".to_string(),
             ExecutionStatus::Failed => "failed".to_string(),
+            ExecutionStatus::Interrupted => "interrupted".to_string(),
+            ExecutionStatus::TimedOut => "timed_out".to_string(),
+            ExecutionStatus::Skipped => "skipped".to_string(),
+            ExecutionStatus::RateLimited => "rate_limited".to_string(),
+            ExecutionStatus::Cancelled => "cancelled".to_string(),
         }
     }
 
@@ -160,7 +487,48 @@ impl ExecutionStatus {
         match s.to_lowercase().as_str() {
             "success" => ExecutionStatus::Success,
             "failed" => ExecutionStatus::Failed,
+            "interrupted" => ExecutionStatus::Interrupted,
+            "timed_out" => ExecutionStatus::TimedOut,
+            "skipped" => ExecutionStatus::Skipped,
+            "rate_limited" => ExecutionStatus::RateLimited,
+            "cancelled" | "canceled" => ExecutionStatus::Cancelled,
             _ => ExecutionStatus::Failed,
         }
     }
 }
+
+impl MisfirePolicy {
+    pub fn to_db_string(&self) -> String {
+        match self {
+            MisfirePolicy::RunOnce => "run_once".to_string(),
+            MisfirePolicy::Skip => "skip".to_string(),
+            MisfirePolicy::RunAll => "run_all".to_string(),
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "skip" => MisfirePolicy::Skip,
+            "run_all" => MisfirePolicy::RunAll,
+            _ => MisfirePolicy::RunOnce,
+        }
+    }
+}
+
+impl DependencyFailurePolicy {
+    pub fn to_db_string(&self) -> String {
+        match self {
+            DependencyFailurePolicy::Skip => "skip".to_string(),
+            DependencyFailurePolicy::Run => "run".to_string(),
+            DependencyFailurePolicy::Wait => "wait".to_string(),
+        }
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "run" => DependencyFailurePolicy::Run,
+            "wait" => DependencyFailurePolicy::Wait,
+            _ => DependencyFailurePolicy::Skip,
+        }
+    }
+}
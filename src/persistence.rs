@@ -1,82 +1,71 @@
+use crate::database::Database;
 use crate::models::{ExecutionHistory, Schedule};
-use anyhow::Result;
+use anyhow::{Context, Result};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-/// 実行履歴とスケジュールのJSONファイル保存先ディレクトリを取得
+/// 以前のGUIが実行履歴とスケジュールをJSONファイルで保存していたディレクトリを取得
 fn get_data_dir() -> Result<PathBuf> {
     let config_dir = crate::config::get_config_dir()?;
-    let data_dir = config_dir.join("data");
-    if !data_dir.exists() {
-        fs::create_dir_all(&data_dir)?;
-    }
-    Ok(data_dir)
+    Ok(config_dir.join("data"))
 }
 
-/// 実行履歴をJSONファイルに保存
-pub fn save_execution_history(history: &[ExecutionHistory]) -> Result<()> {
-    let data_dir = get_data_dir()?;
-    let history_file = data_dir.join("execution_history.json");
-
-    let json = serde_json::to_string_pretty(history)?;
-    fs::write(history_file, json)?;
-
-    Ok(())
+/// JSONファイルから取り込んだ件数
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct ImportSummary {
+    pub schedules: usize,
+    pub history: usize,
 }
 
-/// 実行履歴をJSONファイルから読み込み
-pub fn load_execution_history() -> Result<Vec<ExecutionHistory>> {
+/// 以前のGUIが保存した `schedules.json` / `execution_history.json` をデータベースに取り込む
+///
+/// 取り込んだファイルは `.imported` を付けた名前に変更するため、取り込みは一度だけ行われる。
+/// 同じIDのスケジュール・実行履歴が既にデータベースにある場合はスキップする。
+pub async fn import_legacy_data(db: &Database) -> Result<ImportSummary> {
     let data_dir = get_data_dir()?;
-    let history_file = data_dir.join("execution_history.json");
-
-    if !history_file.exists() {
-        return Ok(Vec::new());
-    }
-
-    let json = fs::read_to_string(history_file)?;
-    let history: Vec<ExecutionHistory> = serde_json::from_str(&json)?;
-
-    Ok(history)
-}
+    let mut summary = ImportSummary::default();
 
-/// スケジュールをJSONファイルに保存
-pub fn save_schedules(schedules: &[Schedule]) -> Result<()> {
-    let data_dir = get_data_dir()?;
     let schedules_file = data_dir.join("schedules.json");
+    if let Some(schedules) = read_json::<Schedule>(&schedules_file)? {
+        for schedule in schedules {
+            if db.get_schedule(&schedule.id).await?.is_none() {
+                db.create_schedule(&schedule).await?;
+                summary.schedules += 1;
+            }
+        }
+        mark_imported(&schedules_file)?;
+    }
 
-    let json = serde_json::to_string_pretty(schedules)?;
-    fs::write(schedules_file, json)?;
+    let history_file = data_dir.join("execution_history.json");
+    if let Some(history) = read_json::<ExecutionHistory>(&history_file)? {
+        for entry in history {
+            if db.get_execution(&entry.id).await?.is_none() {
+                db.create_execution_history(&entry).await?;
+                summary.history += 1;
+            }
+        }
+        mark_imported(&history_file)?;
+    }
 
-    Ok(())
+    Ok(summary)
 }
 
-/// スケジュールをJSONファイルから読み込み
-pub fn load_schedules() -> Result<Vec<Schedule>> {
-    let data_dir = get_data_dir()?;
-    let schedules_file = data_dir.join("schedules.json");
-
-    if !schedules_file.exists() {
-        return Ok(Vec::new());
+/// JSONファイルを読み込む（ファイルがない場合はNone）
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<Option<Vec<T>>> {
+    if !path.exists() {
+        return Ok(None);
     }
 
-    let json = fs::read_to_string(schedules_file)?;
-    let schedules: Vec<Schedule> = serde_json::from_str(&json)?;
-
-    Ok(schedules)
+    let json = fs::read_to_string(path)?;
+    let items = serde_json::from_str(&json)
+        .with_context(|| format!("Failed to import {}", path.display()))?;
+    Ok(Some(items))
 }
 
-/// データディレクトリのクリーンアップ（古いデータの削除）
-#[allow(dead_code)]
-pub fn cleanup_old_data(days: u64) -> Result<()> {
-    use chrono::{Duration, Local};
-
-    let cutoff_date = Local::now() - Duration::days(days as i64);
-    let cutoff_str = cutoff_date.format("%Y-%m-%d %H:%M:%S").to_string();
-
-    // 実行履歴をクリーンアップ
-    let mut history = load_execution_history()?;
-    history.retain(|h| h.executed_at > cutoff_str);
-    save_execution_history(&history)?;
-
+/// 取り込み済みのファイルを `<name>.imported` に変更
+fn mark_imported(path: &Path) -> Result<()> {
+    let mut imported = path.as_os_str().to_owned();
+    imported.push(".imported");
+    fs::rename(path, imported)?;
     Ok(())
 }
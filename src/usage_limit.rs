@@ -0,0 +1,107 @@
+use chrono::{DateTime, Duration, Local, NaiveDateTime, NaiveTime};
+
+/// リセット時刻が読み取れない場合に再実行を試みるまでの待ち時間
+const UNKNOWN_RESET_WAIT_MINUTES: i64 = 60;
+
+/// 利用制限メッセージを探す標準出力の末尾の行数
+const OUTPUT_TAIL_LINES: usize = 5;
+
+/// 出力からClaudeの利用制限メッセージを探し、制限が解除される時刻を返す
+///
+/// 次の形式に対応する。時刻のみの場合は `now` 以降で最初のその時刻とし、
+/// タイムゾーン表記（"(Asia/Tokyo)" など）はローカル時刻とみなす。
+///
+/// - `Claude AI usage limit reached|1736424000`（UNIX時刻）
+/// - `Claude usage limit reached. Your limit will reset at 3pm (Asia/Tokyo).`
+/// - `5-hour limit reached ∙ resets 3:30pm`
+///
+/// 制限メッセージはあるがリセット時刻が読み取れない場合は1時間後を返す。
+pub fn detect_reset_time(output: &str, now: NaiveDateTime) -> Option<NaiveDateTime> {
+    let line = output.lines().find(|line| is_limit_message(line))?;
+
+    let reset_time = parse_epoch(line).or_else(|| {
+        let time = parse_clock(after_reset_keyword(line)?)?;
+        let today = now.date().and_time(time);
+        Some(if today > now {
+            today
+        } else {
+            today + Duration::days(1)
+        })
+    });
+
+    Some(reset_time.unwrap_or(now + Duration::minutes(UNKNOWN_RESET_WAIT_MINUTES)))
+}
+
+/// 失敗したClaudeの実行が利用制限によるものなら、制限が解除される時刻を返す
+///
+/// 制限メッセージは実行の最後に出力されるため、標準エラー出力と標準出力の
+/// 末尾だけを調べる。応答の本文で制限メッセージに触れていても制限とはみなさない。
+pub fn detect_in_failed_run(
+    stdout: &str,
+    stderr: &str,
+    now: NaiveDateTime,
+) -> Option<NaiveDateTime> {
+    let lines: Vec<&str> = stdout.lines().collect();
+    let tail = lines[lines.len().saturating_sub(OUTPUT_TAIL_LINES)..].join("\n");
+    detect_reset_time(stderr, now).or_else(|| detect_reset_time(&tail, now))
+}
+
+fn is_limit_message(line: &str) -> bool {
+    let line = line.to_lowercase();
+    line.contains("usage limit reached")
+        || (line.contains("limit reached") && line.contains("reset"))
+}
+
+/// "usage limit reached|1736424000" 形式のUNIX時刻
+fn parse_epoch(line: &str) -> Option<NaiveDateTime> {
+    let (_, rest) = line.split_once('|')?;
+    let seconds: i64 = rest.trim().parse().ok()?;
+    Some(
+        DateTime::from_timestamp(seconds, 0)?
+            .with_timezone(&Local)
+            .naive_local(),
+    )
+}
+
+/// "resets at 3pm" / "reset at 15:00" / "resets 3:30pm" の時刻部分
+fn after_reset_keyword(line: &str) -> Option<&str> {
+    let lower = line.to_lowercase();
+    let start = lower.find("reset")?;
+    let rest = line.get(start..)?;
+    let rest = rest.trim_start_matches(|c: char| c.is_alphabetic());
+    let rest = rest.trim_start();
+    Some(rest.strip_prefix("at ").unwrap_or(rest))
+}
+
+/// "3pm", "3:30 pm", "15:00" などの時刻を解析する
+fn parse_clock(text: &str) -> Option<NaiveTime> {
+    let text = text.trim_start().to_lowercase();
+    let digits_end = text
+        .find(|c: char| !(c.is_ascii_digit() || c == ':'))
+        .unwrap_or(text.len());
+    let (clock, suffix) = text.split_at(digits_end);
+
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) => (hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?),
+        None => (clock.parse::<u32>().ok()?, 0),
+    };
+
+    let suffix = suffix.trim_start().replace('.', "");
+    let hour = if suffix.starts_with("pm") {
+        if hour == 12 {
+            12
+        } else {
+            hour + 12
+        }
+    } else if suffix.starts_with("am") {
+        if hour == 12 {
+            0
+        } else {
+            hour
+        }
+    } else {
+        hour
+    };
+
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
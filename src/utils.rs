@@ -1,4 +1,4 @@
-use chrono::{Duration, Local, NaiveDateTime, TimeZone};
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
 use std::process::{Command, Output};
 
 /// 現在時刻を"YYYY-MM-DDTHH:MM"形式で取得
@@ -68,3 +68,127 @@ pub fn expand_path(path: &str) -> String {
     }
     path.to_string()
 }
+
+/// "45m", "2h", "1h30m", "90s", "1d" 形式の期間文字列を解析
+///
+/// 表せないほど長い期間はエラーにする。
+pub fn parse_duration(value: &str) -> anyhow::Result<Duration> {
+    let value = value.trim();
+    if value.is_empty() {
+        anyhow::bail!("Duration must not be empty");
+    }
+
+    let too_long = || anyhow::anyhow!("Duration '{value}' is too long");
+    let mut total = Duration::zero();
+    let mut number = String::new();
+
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        let amount: i64 = number
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid duration '{value}'. Use e.g. 45m, 2h, 1h30m"))?;
+        number.clear();
+
+        let part = match c {
+            's' => Duration::try_seconds(amount),
+            'm' => Duration::try_minutes(amount),
+            'h' => Duration::try_hours(amount),
+            'd' => Duration::try_days(amount),
+            _ => anyhow::bail!("Invalid duration unit '{c}' in '{value}'. Use s, m, h or d"),
+        };
+        total = part
+            .and_then(|part| total.checked_add(&part))
+            .ok_or_else(too_long)?;
+    }
+
+    // 単位なしの数値は分として扱う
+    if !number.is_empty() {
+        total = Duration::try_minutes(number.parse().map_err(|_| too_long())?)
+            .and_then(|part| total.checked_add(&part))
+            .ok_or_else(too_long)?;
+    }
+
+    Ok(total)
+}
+
+/// 期間を "1h30m" のような短い形式で表示
+pub fn format_duration(duration: Duration) -> String {
+    let total_seconds = duration.num_seconds();
+    if total_seconds <= 0 {
+        return "0s".to_string();
+    }
+
+    let days = total_seconds / 86_400;
+    let hours = (total_seconds % 86_400) / 3_600;
+    let minutes = (total_seconds % 3_600) / 60;
+    let seconds = total_seconds % 60;
+
+    let mut formatted = String::new();
+    for (amount, unit) in [(days, "d"), (hours, "h"), (minutes, "m"), (seconds, "s")] {
+        if amount > 0 {
+            formatted.push_str(&format!("{amount}{unit}"));
+        }
+    }
+    formatted
+}
+
+/// "10MB", "512KB", "1GB", "2048" 形式のサイズ文字列をバイト数に変換
+pub fn parse_size(value: &str) -> anyhow::Result<u64> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let amount: u64 = number
+        .parse()
+        .map_err(|_| anyhow::anyhow!("Invalid size '{value}'. Use e.g. 10MB, 512KB"))?;
+    let multiplier = match unit.trim().to_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1024,
+        "M" | "MB" => 1024 * 1024,
+        "G" | "GB" => 1024 * 1024 * 1024,
+        _ => anyhow::bail!("Invalid size unit '{unit}' in '{value}'. Use B, KB, MB or GB"),
+    };
+
+    amount
+        .checked_mul(multiplier)
+        .ok_or_else(|| anyhow::anyhow!("Size '{value}' is too large"))
+}
+
+/// 日付の開始時刻（00:00:00）をローカル時刻で取得
+///
+/// 夏時間の切り替えでその時刻が存在しない日は、その日に最初に存在する時刻にする。
+pub fn start_of_day(date: NaiveDate) -> DateTime<Local> {
+    let start = date.and_time(NaiveTime::MIN);
+    nearest_local_time(start, Duration::minutes(1), |time| {
+        time.and_local_timezone(Local).earliest()
+    })
+}
+
+/// 日付の終了時刻（23:59:59）をローカル時刻で取得
+///
+/// 夏時間の切り替えでその時刻が存在しない日は、その日に最後に存在する時刻にする。
+pub fn end_of_day(date: NaiveDate) -> DateTime<Local> {
+    let end = date.and_hms_opt(23, 59, 59).unwrap();
+    nearest_local_time(end, Duration::minutes(-1), |time| {
+        time.and_local_timezone(Local).latest()
+    })
+}
+
+/// `time` から `step` ずつずらして、最初に存在するローカル時刻を探す
+///
+/// 夏時間の切り替えで飛ばされる時間は長くても数時間のため、1日分だけ探す。
+fn nearest_local_time(
+    time: NaiveDateTime,
+    step: Duration,
+    to_local: impl Fn(NaiveDateTime) -> Option<DateTime<Local>>,
+) -> DateTime<Local> {
+    (0..24 * 60)
+        .find_map(|i| time.checked_add_signed(step * i).and_then(&to_local))
+        .unwrap_or_else(|| Local.from_utc_datetime(&time))
+}